@@ -27,7 +27,6 @@ impl CustomMetadata {
             let mut meta = Self::default();
             meta.set_version(0);
             meta.set_status(MetaStatus::Clean);
-            meta.set_app_version(env!("CARGO_PKG_VERSION").to_string());
             init_meta_datetimes_if_empty(&mut meta);
             meta.save(path)?;
             Ok(meta)
@@ -0,0 +1,254 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use okerr::{Result, fail};
+
+use crate::trace::trace_event;
+
+/// A snapshot of the data, taken by a `Backup`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    /// The identifier of the snapshot (sortable, the oldest first).
+    pub id: String,
+    /// Where the snapshot is stored.
+    pub path: PathBuf,
+}
+
+/// Backup snapshots the data before a `Migratex` run, and restores it
+/// on failure (see `Migratex::with_restore_on_failure`) or on demand.
+/// See `FileBackup` for files and directories (and `SqliteBackup` with the `sqlx` feature).
+#[async_trait]
+pub trait Backup: Send + Sync {
+    /// Take a snapshot of the data (and apply the retention, if any).
+    async fn snapshot(&self) -> Result<Snapshot>;
+
+    /// Restore the data from a snapshot.
+    async fn restore(&self, snapshot: &Snapshot) -> Result<()>;
+
+    /// Get the available snapshots, the oldest first.
+    async fn snapshots(&self) -> Result<Vec<Snapshot>>;
+
+    /// Get the most recent snapshot.
+    async fn latest(&self) -> Result<Option<Snapshot>> {
+        Ok(self.snapshots().await?.pop())
+    }
+}
+
+/// FileBackup copies files and directories (e.g. a directory of user data
+/// and the JSON metadata file living alongside) into a backup directory,
+/// one sub-directory per snapshot.
+/// The backup directory cannot be inside a snapshotted path
+/// (the snapshot and the restore fail).
+///
+/// The copies are made with blocking `std::fs` calls, on the task running the migrations:
+/// on an async runtime, snapshot small data sets, or run the migrations
+/// where blocking is allowed (e.g. in `tokio::task::spawn_blocking`,
+/// or with `Migratex::blocking` outside of a runtime).
+///
+/// Example:
+///
+/// ```rust,no_run
+/// use migratex::FileBackup;
+///
+/// let backup = FileBackup::new("data/.backups")
+///     .with_path("data/files")
+///     .with_path("data/metadata.json")
+///     .with_keep(3);
+/// ```
+#[derive(Debug, Clone)]
+pub struct FileBackup {
+    dir: PathBuf,
+    paths: Vec<PathBuf>,
+    keep: Option<usize>,
+}
+
+impl FileBackup {
+    /// Create a new FileBackup, storing the snapshots in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            paths: Vec::new(),
+            keep: None,
+        }
+    }
+
+    /// Add a file or a directory to snapshot.
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.paths.push(path.into());
+        self
+    }
+
+    /// Keep only the `keep` most recent snapshots (at least 1).
+    /// Default: all the snapshots are kept.
+    pub fn with_keep(mut self, keep: usize) -> Self {
+        self.keep = Some(keep.max(1));
+        self
+    }
+
+    /// Get the directory of the snapshots.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Name of the copy of the `index`-th path, in a snapshot.
+    fn entry_name(index: usize, path: &Path) -> String {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        format!("{index}-{name}")
+    }
+
+    /// Check that the backup directory (if it exists) is not inside a snapshotted path:
+    /// it would be copied into itself, and removed by a restore.
+    fn check_dir(&self) -> Result<()> {
+        let Ok(dir) = fs::canonicalize(&self.dir) else {
+            return Ok(());
+        };
+
+        for path in &self.paths {
+            if let Ok(path) = fs::canonicalize(path)
+                && dir.starts_with(&path)
+            {
+                fail!(
+                    "the backup directory {} is inside the snapshotted path {}",
+                    self.dir.display(),
+                    path.display()
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Backup for FileBackup {
+    async fn snapshot(&self) -> Result<Snapshot> {
+        fs::create_dir_all(&self.dir)?;
+        self.check_dir()?;
+
+        let id = snapshot_id(&self.dir, "");
+        let path = self.dir.join(&id);
+        fs::create_dir_all(&path)?;
+
+        for (index, source) in self.paths.iter().enumerate() {
+            // A missing path is snapshotted as missing (removed on restore)
+            if source.exists() {
+                copy_all(source, &path.join(Self::entry_name(index, source)))?;
+            }
+        }
+
+        trace_event!(info, id = %id, path = %path.display(), "migratex backup snapshot taken");
+
+        prune(self.snapshots().await?, self.keep)?;
+
+        Ok(Snapshot { id, path })
+    }
+
+    async fn restore(&self, snapshot: &Snapshot) -> Result<()> {
+        self.check_dir()?;
+
+        for (index, target) in self.paths.iter().enumerate() {
+            remove_all(target)?;
+
+            let copy = snapshot.path.join(Self::entry_name(index, target));
+            if copy.exists() {
+                copy_all(&copy, target)?;
+            }
+        }
+
+        trace_event!(info, id = %snapshot.id, "migratex backup snapshot restored");
+
+        Ok(())
+    }
+
+    async fn snapshots(&self) -> Result<Vec<Snapshot>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut snapshots = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                snapshots.push(Snapshot {
+                    id: entry.file_name().to_string_lossy().into_owned(),
+                    path: entry.path(),
+                });
+            }
+        }
+
+        snapshots.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(snapshots)
+    }
+}
+
+/// Get the identifier of a new snapshot stored in `dir`
+/// (as `<id><extension>`): sortable, the oldest first.
+pub(crate) fn snapshot_id(dir: &Path, extension: &str) -> String {
+    let base = chrono::Utc::now().format("%Y%m%dT%H%M%S%.6fZ").to_string();
+
+    // Unique, even for snapshots taken in the same microsecond
+    let mut id = base.clone();
+    let mut n = 1;
+    while dir.join(format!("{id}{extension}")).exists() {
+        id = format!("{base}-{n}");
+        n += 1;
+    }
+
+    id
+}
+
+/// Remove the oldest snapshots (sorted, the oldest first), beyond the retention (if any).
+pub(crate) fn prune(snapshots: Vec<Snapshot>, keep: Option<usize>) -> Result<()> {
+    let Some(keep) = keep else {
+        return Ok(());
+    };
+
+    let excess = snapshots.len().saturating_sub(keep);
+    for snapshot in snapshots.into_iter().take(excess) {
+        remove_all(&snapshot.path)?;
+        trace_event!(debug, id = %snapshot.id, "migratex backup snapshot pruned");
+    }
+
+    Ok(())
+}
+
+/// Copy a file or a directory (recursively).
+fn copy_all(from: &Path, to: &Path) -> Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_all(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else {
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(from, to)?;
+    }
+
+    Ok(())
+}
+
+/// Remove a file or a directory (recursively), if it exists.
+fn remove_all(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path)?;
+    } else if path.exists() {
+        fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+
+use okerr::Result;
+
+use crate::{Metadata, Migratex, Version};
+
+/// BlockingMigratex runs the operations of a `Migratex` synchronously
+/// (see `Migratex::blocking`), for the callers without an async runtime.
+///
+/// The futures are run on the current thread, without executor:
+/// the migrations must not depend on a runtime (e.g. tokio for SQLx),
+/// otherwise use the `block_on` of that runtime.
+///
+/// Example:
+///
+/// ```rust,ignore
+/// let mut mx = Migratex::new(&mut ctx, &mut meta, migrations);
+/// mx.blocking().migrate_to_latest()?;
+/// ```
+pub struct BlockingMigratex<'a, 'm, 'c, MigContext, M: Metadata<V>, V: Version = i32> {
+    mx: &'a mut Migratex<'m, 'c, MigContext, M, V>,
+}
+
+impl<'m, 'c, MigContext, M: Metadata<V>, V: Version> Migratex<'m, 'c, MigContext, M, V> {
+    /// Get a blocking facade, running the operations synchronously (see `BlockingMigratex`).
+    pub fn blocking(&mut self) -> BlockingMigratex<'_, 'm, 'c, MigContext, M, V> {
+        BlockingMigratex { mx: self }
+    }
+}
+
+impl<MigContext, M: Metadata<V>, V: Version> BlockingMigratex<'_, '_, '_, MigContext, M, V> {
+    /// See `Migratex::migrate_to_latest`.
+    pub fn migrate_to_latest(&mut self) -> Result<()> {
+        block_on(self.mx.migrate_to_latest())
+    }
+
+    /// See `Migratex::migrate_to_zero`.
+    pub fn migrate_to_zero(&mut self) -> Result<()> {
+        block_on(self.mx.migrate_to_zero())
+    }
+
+    /// See `Migratex::migrate_to`.
+    pub fn migrate_to(&mut self, target: V) -> Result<()> {
+        block_on(self.mx.migrate_to(target))
+    }
+
+    /// See `Migratex::migrate_next`.
+    pub fn migrate_next(&mut self) -> Result<()> {
+        block_on(self.mx.migrate_next())
+    }
+
+    /// See `Migratex::migrate_prev`.
+    pub fn migrate_prev(&mut self) -> Result<()> {
+        block_on(self.mx.migrate_prev())
+    }
+
+    /// See `Migratex::redo`.
+    pub fn redo(&mut self) -> Result<()> {
+        block_on(self.mx.redo())
+    }
+
+    /// See `Migratex::redo_n`.
+    pub fn redo_n(&mut self, n: usize) -> Result<()> {
+        block_on(self.mx.redo_n(n))
+    }
+
+    /// See `Migratex::reset`.
+    pub fn reset(&mut self) -> Result<()> {
+        block_on(self.mx.reset())
+    }
+}
+
+/// Wake a thread parked by `block_on`.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Run a future to completion on the current thread, parked while the future is pending.
+fn block_on<F: Future>(fut: F) -> F::Output {
+    let mut fut = pin!(fut);
+    let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        if let Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+            return output;
+        }
+        thread::park();
+    }
+}
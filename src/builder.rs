@@ -0,0 +1,241 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use okerr::Result;
+
+use crate::migratex::Slot;
+use crate::{
+    AppVersionPolicy, Backup, BoxMigration, BoxRepeatable, CancellationToken, DataAhead, Metadata,
+    Migratex, MigratexError, Migration, MigrationObserver, OutOfOrder, Progress, RetryPolicy,
+    Version,
+};
+
+/// MigratexBuilder configures a `Migratex` step by step (see `Migratex::builder`),
+/// the configuration is validated by `build`.
+///
+/// Example:
+///
+/// ```rust,ignore
+/// let mut mx = Migratex::builder()
+///     .with_context(&mut ctx)
+///     .with_metadata(&mut meta)
+///     .with_migrations(migrations)
+///     .with_step_timeout(Duration::from_secs(60))
+///     .with_app_version(env!("CARGO_PKG_VERSION"))
+///     .build()?;
+/// ```
+pub struct MigratexBuilder<'m, 'c, MigContext, M: Metadata<V>, V: Version = i32> {
+    /// The Migratex being configured (its context and metadata are missing until given).
+    mx: Migratex<'m, 'c, MigContext, M, V>,
+}
+
+impl<'m, 'c, MigContext, M: Metadata<V>, V: Version> Migratex<'m, 'c, MigContext, M, V> {
+    /// Create a builder to configure a Migratex (see `MigratexBuilder`).
+    pub fn builder() -> MigratexBuilder<'m, 'c, MigContext, M, V> {
+        MigratexBuilder::new()
+    }
+}
+
+impl<'m, 'c, MigContext, M: Metadata<V>, V: Version> MigratexBuilder<'m, 'c, MigContext, M, V> {
+    /// Create a new (empty) builder.
+    pub fn new() -> Self {
+        Self {
+            mx: Migratex::with_slots(Slot::Missing, Slot::Missing, Vec::new()),
+        }
+    }
+
+    /// Configure the Migratex with one of its `with_*` setters.
+    fn map(
+        self,
+        f: impl FnOnce(Migratex<'m, 'c, MigContext, M, V>) -> Migratex<'m, 'c, MigContext, M, V>,
+    ) -> Self {
+        Self { mx: f(self.mx) }
+    }
+
+    /// Set the migration context (borrowed).
+    pub fn with_context(mut self, ctx: &'c mut MigContext) -> Self {
+        self.mx.ctx = Slot::Borrowed(ctx);
+        self
+    }
+
+    /// Set the migration context (owned, see `Migratex::owned`).
+    pub fn with_owned_context(mut self, ctx: MigContext) -> Self {
+        self.mx.ctx = Slot::Owned(ctx);
+        self
+    }
+
+    /// Set the metadata (borrowed).
+    pub fn with_metadata(mut self, meta: &'m mut M) -> Self {
+        self.mx.meta = Slot::Borrowed(meta);
+        self
+    }
+
+    /// Set the metadata (owned, see `Migratex::owned`).
+    pub fn with_owned_metadata(mut self, meta: M) -> Self {
+        self.mx.meta = Slot::Owned(meta);
+        self
+    }
+
+    /// Add migrations.
+    pub fn with_migrations(mut self, migrations: Vec<BoxMigration<MigContext, V>>) -> Self {
+        self.mx.migrations.extend(migrations);
+        self
+    }
+
+    /// Add a migration.
+    pub fn with_migration(mut self, migration: impl Migration<MigContext, V> + 'static) -> Self {
+        self.mx.migrations.push(Box::new(migration));
+        self
+    }
+
+    /// Add repeatable migrations (see `Migratex::with_repeatable_migrations`).
+    pub fn with_repeatable_migrations(
+        mut self,
+        migrations: Vec<BoxRepeatable<MigContext>>,
+    ) -> Self {
+        self.mx.repeatable_migrations.extend(migrations);
+        self
+    }
+
+    /// See `Migratex::with_force_irreversible`.
+    pub fn with_force_irreversible(self, force: bool) -> Self {
+        self.map(|mx| mx.with_force_irreversible(force))
+    }
+
+    /// See `Migratex::with_out_of_order`.
+    pub fn with_out_of_order(self, out_of_order: OutOfOrder) -> Self {
+        self.map(|mx| mx.with_out_of_order(out_of_order))
+    }
+
+    /// See `Migratex::with_observer`.
+    pub fn with_observer(self, observer: impl MigrationObserver<V> + 'static) -> Self {
+        self.map(|mx| mx.with_observer(observer))
+    }
+
+    /// See `Migratex::with_progress`.
+    pub fn with_progress(self, progress: Progress<V>) -> Self {
+        self.map(|mx| mx.with_progress(progress))
+    }
+
+    /// See `Migratex::with_timeout`.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        self.map(|mx| mx.with_timeout(timeout))
+    }
+
+    /// See `Migratex::with_step_timeout`.
+    pub fn with_step_timeout(self, timeout: Duration) -> Self {
+        self.map(|mx| mx.with_step_timeout(timeout))
+    }
+
+    /// See `Migratex::with_cancellation`.
+    pub fn with_cancellation(self, token: CancellationToken) -> Self {
+        self.map(|mx| mx.with_cancellation(token))
+    }
+
+    /// See `Migratex::with_retry`.
+    pub fn with_retry(self, policy: RetryPolicy) -> Self {
+        self.map(|mx| mx.with_retry(policy))
+    }
+
+    /// See `Migratex::with_backup`.
+    pub fn with_backup(self, backup: impl Backup + 'static) -> Self {
+        self.map(|mx| mx.with_backup(backup))
+    }
+
+    /// See `Migratex::with_restore_on_failure` (requires a backup).
+    pub fn with_restore_on_failure(self, restore: bool) -> Self {
+        self.map(|mx| mx.with_restore_on_failure(restore))
+    }
+
+    /// See `Migratex::with_tags`.
+    pub fn with_tags<T: Into<String>>(self, tags: impl IntoIterator<Item = T>) -> Self {
+        self.map(|mx| mx.with_tags(tags))
+    }
+
+    /// See `Migratex::with_data_ahead`.
+    pub fn with_data_ahead(self, data_ahead: DataAhead) -> Self {
+        self.map(|mx| mx.with_data_ahead(data_ahead))
+    }
+
+    /// See `Migratex::with_production`.
+    pub fn with_production(self, production: bool) -> Self {
+        self.map(|mx| mx.with_production(production))
+    }
+
+    /// See `Migratex::with_app_version`.
+    pub fn with_app_version(self, app_version: impl Into<String>) -> Self {
+        self.map(|mx| mx.with_app_version(app_version))
+    }
+
+    /// See `Migratex::with_app_version_policy`.
+    pub fn with_app_version_policy(self, policy: AppVersionPolicy) -> Self {
+        self.map(|mx| mx.with_app_version_policy(policy))
+    }
+
+    /// Validate the configuration and build the Migratex.
+    /// Fails (`MigratexError::InvalidConfig`) without context or metadata,
+    /// with several migrations of the same version (or repeatable migrations of the same name),
+    /// with a zero timeout, or to restore on failure without backup.
+    pub fn build(self) -> Result<Migratex<'m, 'c, MigContext, M, V>> {
+        let mx = self.mx;
+        let invalid = |reason: &str| -> okerr::Error {
+            MigratexError::<V>::InvalidConfig {
+                reason: reason.to_string(),
+            }
+            .into()
+        };
+
+        if matches!(mx.ctx, Slot::Missing) {
+            return Err(invalid("the context is missing"));
+        }
+
+        if matches!(mx.meta, Slot::Missing) {
+            return Err(invalid("the metadata is missing"));
+        }
+
+        let mut versions = BTreeSet::new();
+        if let Some(m) = mx.migrations.iter().find(|m| !versions.insert(m.version())) {
+            return Err(invalid(&format!(
+                "several migrations have the version {}",
+                m.version()
+            )));
+        }
+
+        let mut names = BTreeSet::new();
+        if let Some(m) = mx
+            .repeatable_migrations
+            .iter()
+            .find(|m| !names.insert(m.name()))
+        {
+            return Err(invalid(&format!(
+                "several repeatable migrations are named {}",
+                m.name()
+            )));
+        }
+
+        if mx.run_timeout.is_some_and(|t| t.is_zero())
+            || mx.step_timeout.is_some_and(|t| t.is_zero())
+        {
+            return Err(invalid("a timeout cannot be zero"));
+        }
+
+        if mx.restore_on_failure && mx.backup.is_none() {
+            return Err(invalid("restore on failure requires a backup"));
+        }
+
+        Ok(mx)
+    }
+}
+
+impl<MigContext, M: Metadata<V>, V: Version> Default for MigratexBuilder<'_, '_, MigContext, M, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
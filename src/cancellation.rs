@@ -0,0 +1,119 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+/// Cancellation state shared by the token clones.
+#[derive(Default)]
+struct CancellationInner {
+    cancelled: AtomicBool,
+    /// The wakers of the pending `cancelled` futures, by slot key.
+    wakers: Mutex<BTreeMap<u64, Waker>>,
+    next_key: AtomicU64,
+}
+
+/// CancellationToken stops a `Migratex` run cleanly (e.g. on a shutdown signal).
+///
+/// It is a cheap handle (clone it): give it to `Migratex` with `with_cancellation`,
+/// and call `cancel` from anywhere (another task, a signal handler, etc).
+/// `Migratex` checks it between the steps: the running step is not interrupted,
+/// the next ones are not run.
+/// Put a clone in the migration context so the long-running migrations can stop early too
+/// (`is_cancelled` or `cancelled().await`).
+#[derive(Clone, Default)]
+pub struct CancellationToken {
+    inner: Arc<CancellationInner>,
+}
+
+impl CancellationToken {
+    /// Create a new CancellationToken.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel: notify all the clones of this token.
+    pub fn cancel(&self) {
+        self.inner.cancelled.store(true, Ordering::SeqCst);
+
+        let wakers = std::mem::take(&mut *self.inner.wakers.lock().unwrap());
+        for waker in wakers.into_values() {
+            waker.wake();
+        }
+    }
+
+    /// Whether the token is cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Wait until the token is cancelled.
+    pub async fn cancelled(&self) {
+        Cancelled {
+            token: self,
+            key: None,
+        }
+        .await
+    }
+}
+
+/// Future of `CancellationToken::cancelled`: its waker has a slot in the token,
+/// updated when polled again and removed when dropped (e.g. checked alongside each item).
+struct Cancelled<'a> {
+    token: &'a CancellationToken,
+    key: Option<u64>,
+}
+
+impl Future for Cancelled<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.token.is_cancelled() {
+            return Poll::Ready(());
+        }
+
+        let this = &mut *self;
+        let inner = &this.token.inner;
+        let key = *this
+            .key
+            .get_or_insert_with(|| inner.next_key.fetch_add(1, Ordering::Relaxed));
+
+        {
+            let mut wakers = inner.wakers.lock().unwrap();
+            if !wakers.get(&key).is_some_and(|w| w.will_wake(cx.waker())) {
+                wakers.insert(key, cx.waker().clone());
+            }
+        }
+
+        // Cancelled while registering the waker
+        if this.token.is_cancelled() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Cancelled<'_> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key {
+            self.token.inner.wakers.lock().unwrap().remove(&key);
+        }
+    }
+}
+
+impl std::fmt::Debug for CancellationToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .finish()
+    }
+}
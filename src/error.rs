@@ -0,0 +1,116 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+use std::time::Duration;
+
+use okerr::derive::Error;
+
+use crate::{Dependency, Version};
+
+/// The errors raised by Migratex itself (not by the migrations).
+/// They are returned wrapped in an `okerr::Error`,
+/// use `err.downcast_ref::<MigratexError>()` to match them
+/// (or `MigratexError<V>` when the version type is not `i32`).
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum MigratexError<V: Version = i32> {
+    /// A downgrade crosses an irreversible migration.
+    #[error("migration {version} ({name}) is irreversible, cannot migrate down to {target}")]
+    Irreversible { version: V, name: String, target: V },
+
+    /// A version is inside the range replaced by a squashed migration,
+    /// the old migrations of this range are no longer available.
+    #[error(
+        "version {at} is inside the range replaced by the squashed migration {version} ({name}, from {from})"
+    )]
+    InsideSquash {
+        version: V,
+        name: String,
+        from: V,
+        at: V,
+    },
+
+    /// Migrations older than the current version were never applied
+    /// (merged out-of-order).
+    #[error(
+        "migrations {versions:?} are older than the current version {current} but were never applied"
+    )]
+    OutOfOrder { versions: Vec<V>, current: V },
+
+    /// The configuration of a Migratex is invalid (see `MigratexBuilder::build`).
+    #[error("invalid Migratex configuration: {reason}")]
+    InvalidConfig { reason: String },
+
+    /// A development operation (e.g. `redo`, `reset`) was refused on production data
+    /// (see `Migratex::with_production`).
+    #[error("{operation} is refused on production data")]
+    Production { operation: String },
+
+    /// The version of the data is greater than the latest known migration
+    /// (see `DataAhead`).
+    #[error("the data is at version {version}, ahead of the latest known migration {latest}")]
+    DataAhead { version: V, latest: V },
+
+    /// The data was migrated by a newer version of the application
+    /// (see `AppVersionPolicy`).
+    #[error(
+        "the data was migrated by the app version {stored}, newer than the running app version {app_version}"
+    )]
+    NewerAppVersion { app_version: String, stored: String },
+
+    /// A migration step did not complete in time (the step is interrupted).
+    #[error("migration {version} ({name}) timed out after {timeout:?}")]
+    Timeout {
+        version: V,
+        name: String,
+        timeout: Duration,
+    },
+
+    /// The run did not complete in time (stopped between two steps, at `version`).
+    #[error("migration run timed out after {timeout:?}, stopped at version {version}")]
+    RunTimeout { timeout: Duration, version: V },
+
+    /// The pre-condition of a migration step failed (`Migration::check_before`),
+    /// nothing was run.
+    #[error("migration {version} ({name}) pre-condition failed: {reason}")]
+    CheckFailed {
+        version: V,
+        name: String,
+        reason: String,
+    },
+
+    /// The post-condition of a migration step failed (`Migration::verify_after`).
+    /// The step is undone when possible (`rolled_back`).
+    #[error(
+        "migration {version} ({name}) verification failed (rolled back: {rolled_back}): {reason}"
+    )]
+    VerifyFailed {
+        version: V,
+        name: String,
+        reason: String,
+        rolled_back: bool,
+    },
+
+    /// A migration of a track depends on a version (of a track) the run cannot reach.
+    #[error("migration {track}/{version} ({name}) depends on {dependency}, but {reason}")]
+    UnsatisfiedDependency {
+        track: String,
+        version: V,
+        name: String,
+        dependency: Dependency<V>,
+        reason: String,
+    },
+
+    /// The dependencies between the migrations of the tracks form a cycle
+    /// (`steps` are the blocked migrations, as `track/version`).
+    #[error("dependency cycle between the migrations {steps:?}")]
+    DependencyCycle { steps: Vec<String> },
+
+    /// The run was cancelled (stopped between two steps, at `version`).
+    #[error("migration run cancelled, stopped at version {version}")]
+    Cancelled { version: V },
+}
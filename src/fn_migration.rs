@@ -0,0 +1,93 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+use std::future::Future;
+use std::pin::Pin;
+
+use async_trait::async_trait;
+use okerr::Result;
+
+use crate::{Migration, Version};
+
+/// A boxed future, `Send` and bound to the lifetime `'a`.
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// The type of the `up` and `down` closures of a [`FnMigration`].
+type MigrationFn<MigContext> =
+    Box<dyn for<'a> Fn(&'a mut MigContext) -> BoxFuture<'a, Result<()>> + Send + Sync>;
+
+/// FnMigration is a migration defined by closures, without defining a struct.
+/// It can be mixed freely with struct-based migrations in the same list.
+///
+/// # Example
+///
+/// ```rust
+/// use migratex::{BoxMigration, FnMigration};
+///
+/// #[derive(Default)]
+/// struct MigContext {
+///     counter: i32,
+/// }
+///
+/// let migrations: Vec<BoxMigration<MigContext>> = vec![Box::new(FnMigration::new(
+///     1,
+///     "increment_counter",
+///     |ctx: &mut MigContext| {
+///         Box::pin(async move {
+///             ctx.counter += 1;
+///             Ok(())
+///         })
+///     },
+///     |ctx: &mut MigContext| {
+///         Box::pin(async move {
+///             ctx.counter -= 1;
+///             Ok(())
+///         })
+///     },
+/// ))];
+/// ```
+pub struct FnMigration<MigContext, V = i32> {
+    version: V,
+    name: String,
+    up: MigrationFn<MigContext>,
+    down: MigrationFn<MigContext>,
+}
+
+impl<MigContext, V: Version> FnMigration<MigContext, V> {
+    /// Create a new FnMigration from the `up` and `down` closures.
+    pub fn new<U, D>(version: V, name: impl Into<String>, up: U, down: D) -> Self
+    where
+        U: for<'a> Fn(&'a mut MigContext) -> BoxFuture<'a, Result<()>> + Send + Sync + 'static,
+        D: for<'a> Fn(&'a mut MigContext) -> BoxFuture<'a, Result<()>> + Send + Sync + 'static,
+    {
+        Self {
+            version,
+            name: name.into(),
+            up: Box::new(up),
+            down: Box::new(down),
+        }
+    }
+}
+
+#[async_trait]
+impl<MigContext: Send, V: Version> Migration<MigContext, V> for FnMigration<MigContext, V> {
+    fn version(&self) -> V {
+        self.version.clone()
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    async fn up(&self, ctx: &mut MigContext) -> Result<()> {
+        (self.up)(ctx).await
+    }
+
+    async fn down(&self, ctx: &mut MigContext) -> Result<()> {
+        (self.down)(ctx).await
+    }
+}
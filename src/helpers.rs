@@ -7,16 +7,35 @@
 
 use okerr::Result;
 
-use crate::Metadata;
+use crate::trace::trace_event;
+use crate::{MetaStatus, Metadata, Version};
 
 /// Calls `init_meta_datetimes_if_empty` and returns `Ok(meta)`.
-pub fn meta_loaded<M: Metadata>(mut meta: M) -> Result<M> {
+/// With the `tracing` feature, it also emits the "metadata loaded" event
+/// (and a warning when the metadata is dirty: a previous run was interrupted).
+pub fn meta_loaded<V: Version, M: Metadata<V>>(mut meta: M) -> Result<M> {
     init_meta_datetimes_if_empty(&mut meta);
+
+    trace_event!(
+        debug,
+        version = %meta.version(),
+        status = meta.to_status_str(),
+        "migratex metadata loaded"
+    );
+
+    if meta.status() == MetaStatus::Migrating {
+        trace_event!(
+            warn,
+            version = %meta.version(),
+            "migratex metadata is dirty (a previous migration run was interrupted)"
+        );
+    }
+
     Ok(meta)
 }
 
 /// Initialize created_at / updated_at if created_at is empty.
-pub fn init_meta_datetimes_if_empty(meta: &mut impl Metadata) {
+pub fn init_meta_datetimes_if_empty<V: Version>(meta: &mut impl Metadata<V>) {
     if meta.created_at().is_empty() {
         let now = chrono::Utc::now().to_rfc3339();
         *meta.created_at_mut() = now.clone();
@@ -25,14 +44,18 @@ pub fn init_meta_datetimes_if_empty(meta: &mut impl Metadata) {
 }
 
 // A convenient macro to generate all the accessors for concrete Metadata.
+// The version type is `i32` by default, or the given type (e.g. `metadata_accessors!(i64)`).
 #[macro_export]
 macro_rules! metadata_accessors {
     () => {
-        fn version(&self) -> i32 {
-            self.version
+        $crate::metadata_accessors!(i32);
+    };
+    ($v:ty) => {
+        fn version(&self) -> $v {
+            self.version.clone()
         }
 
-        fn version_mut(&mut self) -> &mut i32 {
+        fn version_mut(&mut self) -> &mut $v {
             &mut self.version
         }
 
@@ -69,3 +92,71 @@ macro_rules! metadata_accessors {
         }
     };
 }
+
+// A convenient macro to generate the applied-set accessors for concrete Metadata
+// having an `applied: BTreeSet<V>` field.
+// The version type is `i32` by default, or the given type (e.g. `applied_versions_accessors!(i64)`).
+#[macro_export]
+macro_rules! applied_versions_accessors {
+    () => {
+        $crate::applied_versions_accessors!(i32);
+    };
+    ($v:ty) => {
+        fn applied_versions(&self) -> Option<&std::collections::BTreeSet<$v>> {
+            Some(&self.applied)
+        }
+
+        fn applied_versions_mut(&mut self) -> Option<&mut std::collections::BTreeSet<$v>> {
+            Some(&mut self.applied)
+        }
+    };
+}
+
+// A convenient macro to generate the skipped-set accessors for concrete Metadata
+// having a `skipped: BTreeSet<V>` field.
+// The version type is `i32` by default, or the given type (e.g. `skipped_versions_accessors!(i64)`).
+#[macro_export]
+macro_rules! skipped_versions_accessors {
+    () => {
+        $crate::skipped_versions_accessors!(i32);
+    };
+    ($v:ty) => {
+        fn skipped_versions(&self) -> Option<&std::collections::BTreeSet<$v>> {
+            Some(&self.skipped)
+        }
+
+        fn skipped_versions_mut(&mut self) -> Option<&mut std::collections::BTreeSet<$v>> {
+            Some(&mut self.skipped)
+        }
+    };
+}
+
+// A convenient macro to generate the checksums accessors for concrete Metadata
+// having a `checksums: BTreeMap<String, String>` field.
+#[macro_export]
+macro_rules! checksums_accessors {
+    () => {
+        fn checksums(&self) -> Option<&std::collections::BTreeMap<String, String>> {
+            Some(&self.checksums)
+        }
+
+        fn checksums_mut(&mut self) -> Option<&mut std::collections::BTreeMap<String, String>> {
+            Some(&mut self.checksums)
+        }
+    };
+}
+
+// A convenient macro to generate the app-version-recorded accessors for concrete Metadata
+// having an `app_version_recorded: bool` field.
+#[macro_export]
+macro_rules! app_version_recorded_accessors {
+    () => {
+        fn app_version_recorded(&self) -> bool {
+            self.app_version_recorded
+        }
+
+        fn app_version_recorded_mut(&mut self) -> Option<&mut bool> {
+            Some(&mut self.app_version_recorded)
+        }
+    };
+}
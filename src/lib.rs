@@ -17,16 +17,45 @@
 //!  - [https://github.com/nicolab/migratex](https://github.com/nicolab/migratex)
 //!  - [Examples](https://github.com/nicolab/migratex/tree/main/examples)
 
+mod backup;
+mod blocking;
+mod builder;
+mod cancellation;
+mod error;
+mod fn_migration;
 mod helpers;
 mod metadata;
 mod migratex;
 mod migration;
+mod observer;
+mod plan;
+mod progress;
+mod repeatable;
+mod retry;
 mod store;
+pub mod testing;
+mod timer;
+mod trace;
+mod tracks;
+mod version;
 
+pub use backup::*;
+pub use blocking::*;
+pub use builder::*;
+pub use cancellation::*;
+pub use error::*;
+pub use fn_migration::*;
 pub use helpers::*;
 pub use metadata::*;
 pub use migratex::*;
 pub use migration::*;
+pub use observer::*;
+pub use plan::*;
+pub use progress::*;
+pub use repeatable::*;
+pub use retry::*;
+pub use tracks::*;
+pub use version::*;
 
 #[cfg(any(feature = "json", feature = "sqlx"))]
 pub use store::*;
@@ -5,35 +5,33 @@
 // information and documentation: https://github.com/nicolab/migratex
 // -----------------------------------------------------------------------------
 
-#[cfg(feature = "json")]
-use serde;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::Version;
 
 /// The status of a migration.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 #[cfg_attr(feature = "json", derive(serde::Serialize, serde::Deserialize))]
 pub enum MetaStatus {
+    #[default]
     Clean,
     Migrating,
     Failed,
 }
 
-impl Default for MetaStatus {
-    fn default() -> Self {
-        Self::Clean
-    }
-}
-
-pub trait Metadata {
+/// Metadata of the migrations: the current version, the status, etc.
+/// The version type `V` is `i32` by default (see [`Version`]).
+pub trait Metadata<V: Version = i32> {
     //
     // -- CORE: logical fields
     //
 
     /// Migration version.
-    fn version(&self) -> i32;
-    fn version_mut(&mut self) -> &mut i32;
+    fn version(&self) -> V;
+    fn version_mut(&mut self) -> &mut V;
 
-    /// Application version.
-    /// By default, it is the project `package.version` field (Cargo).
+    /// Version of the application that last migrated the data
+    /// (set by `Migratex::with_app_version`, empty until then).
     fn app_version(&self) -> &str;
     fn app_version_mut(&mut self) -> &mut String;
 
@@ -49,6 +47,59 @@ pub trait Metadata {
     fn updated_at(&self) -> &str;
     fn updated_at_mut(&mut self) -> &mut String;
 
+    //
+    // -- OPTIONAL: applied-set model
+    //
+
+    /// The versions of the applied migrations (applied-set model).
+    /// `None` (default) when the metadata only tracks the highest applied version.
+    /// Implement it (see `applied_versions_accessors!`) to detect the migrations
+    /// merged out-of-order (older than the current version but never applied).
+    fn applied_versions(&self) -> Option<&BTreeSet<V>> {
+        None
+    }
+
+    fn applied_versions_mut(&mut self) -> Option<&mut BTreeSet<V>> {
+        None
+    }
+
+    /// The versions of the skipped migrations: filtered out by their tags
+    /// (see `Migratex::with_tags`), but counted as applied so the version still advances.
+    /// `None` (default) when the metadata does not record them
+    /// (see `skipped_versions_accessors!`).
+    fn skipped_versions(&self) -> Option<&BTreeSet<V>> {
+        None
+    }
+
+    fn skipped_versions_mut(&mut self) -> Option<&mut BTreeSet<V>> {
+        None
+    }
+
+    /// The checksums of the applied repeatable migrations, by name
+    /// (see `RepeatableMigration`).
+    /// `None` (default) when the metadata does not record them
+    /// (see `checksums_accessors!`): the repeatable migrations then run on every run.
+    fn checksums(&self) -> Option<&BTreeMap<String, String>> {
+        None
+    }
+
+    fn checksums_mut(&mut self) -> Option<&mut BTreeMap<String, String>> {
+        None
+    }
+
+    /// Whether the `app_version` was set by the application (see `Migratex::with_app_version`).
+    /// The stores of the previous releases initialized it to the version of migratex itself:
+    /// this legacy value is not checked, until the app version is recorded.
+    /// `true` (default) when the metadata does not track it
+    /// (see `app_version_recorded_accessors!`): the `app_version` is always checked.
+    fn app_version_recorded(&self) -> bool {
+        true
+    }
+
+    fn app_version_recorded_mut(&mut self) -> Option<&mut bool> {
+        None
+    }
+
     //
     // -- Helpers (with default implementations)
     //
@@ -68,14 +119,57 @@ pub trait Metadata {
     }
 
     /// Set the version and update `updated_at`.
-    fn set_version(&mut self, v: i32) {
+    fn set_version(&mut self, v: V) {
         *self.version_mut() = v;
         self.touch_updated();
     }
 
-    /// Set the app_version and update `updated_at`.
+    /// Add a version to the applied set (if any) and update `updated_at`.
+    fn mark_applied(&mut self, v: V) {
+        if let Some(applied) = self.applied_versions_mut() {
+            applied.insert(v);
+            self.touch_updated();
+        }
+    }
+
+    /// Remove a version from the applied set (if any) and update `updated_at`.
+    fn mark_unapplied(&mut self, v: V) {
+        if let Some(applied) = self.applied_versions_mut() {
+            applied.remove(&v);
+            self.touch_updated();
+        }
+    }
+
+    /// Add a version to the skipped set (if any) and update `updated_at`.
+    fn mark_skipped(&mut self, v: V) {
+        if let Some(skipped) = self.skipped_versions_mut() {
+            skipped.insert(v);
+            self.touch_updated();
+        }
+    }
+
+    /// Remove a version from the skipped set (if any) and update `updated_at`.
+    fn mark_unskipped(&mut self, v: V) {
+        if let Some(skipped) = self.skipped_versions_mut() {
+            skipped.remove(&v);
+            self.touch_updated();
+        }
+    }
+
+    /// Set the checksum of a repeatable migration (if recorded) and update `updated_at`.
+    fn set_checksum(&mut self, name: String, checksum: String) {
+        if let Some(checksums) = self.checksums_mut() {
+            checksums.insert(name, checksum);
+            self.touch_updated();
+        }
+    }
+
+    /// Set the app_version (marked as recorded, if tracked) and update `updated_at`.
     fn set_app_version(&mut self, v: String) {
         *self.app_version_mut() = v;
+        if let Some(recorded) = self.app_version_recorded_mut() {
+            *recorded = true;
+        }
         self.touch_updated();
     }
 
@@ -5,50 +5,324 @@
 // information and documentation: https://github.com/nicolab/migratex
 // -----------------------------------------------------------------------------
 
-use okerr::Result;
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::{Deref, DerefMut};
+use std::time::{Duration, Instant};
 
+use okerr::{Context, Result};
+
+use crate::Backup;
 use crate::BoxMigration;
+use crate::BoxRepeatable;
+use crate::CancellationToken;
 use crate::Metadata;
+use crate::Migration;
+use crate::MigrationObserver;
+use crate::RetryPolicy;
+use crate::timer::{sleep, timeout};
+use crate::trace::{TraceSpan, trace_event};
+use crate::version::cmp_app_versions;
+use crate::{
+    AppVersionPolicy, DataAhead, Direction, MigratexError, MigrationPlan, OutOfOrder, PlanStep,
+    Progress, Snapshot, Version,
+};
 
 /// Migratex manages the migrations, this is the main struct.
 /// Think of it as a "migration manager", "migrator", "runner").
 /// It can be used to migrate database / data / files / binaries, etc from one version to another.
-pub struct Migratex<'m, 'c, MigContext, M: Metadata> {
+/// The version type `V` is `i32` by default (see [`Version`]).
+///
+/// The context and the metadata are either borrowed (`new`) or owned (`owned`).
+/// An owned Migratex can be stored in the application state, returned by a function,
+/// or moved to a task (e.g. `tokio::spawn`), then give them back (`into_parts`).
+pub struct Migratex<'m, 'c, MigContext, M: Metadata<V>, V: Version = i32> {
     /// The migration context, passed to each migration.
-    ctx: &'c mut MigContext,
+    pub(crate) ctx: Slot<'c, MigContext>,
     /// The metadata, passed to each migration.
-    meta: &'m mut M,
+    pub(crate) meta: Slot<'m, M>,
     /// The migrations list.
-    migrations: Vec<BoxMigration<MigContext>>,
+    pub(crate) migrations: Vec<BoxMigration<MigContext, V>>,
+    /// Allow a downgrade to cross irreversible migrations.
+    pub(crate) force_irreversible: bool,
+    /// What to do with the migrations merged out-of-order.
+    pub(crate) out_of_order: OutOfOrder,
+    /// The observers notified of the migration events.
+    pub(crate) observers: Vec<Box<dyn MigrationObserver<V>>>,
+    /// The maximum duration of a run.
+    pub(crate) run_timeout: Option<Duration>,
+    /// The default maximum duration of a step.
+    pub(crate) step_timeout: Option<Duration>,
+    /// The token to stop a run between two steps.
+    pub(crate) cancellation: Option<CancellationToken>,
+    /// The retry policy of the retryable migrations.
+    pub(crate) retry: Option<RetryPolicy>,
+    /// The backup snapshotting the data before a run.
+    pub(crate) backup: Option<Box<dyn Backup>>,
+    /// Restore the snapshot when a run fails.
+    pub(crate) restore_on_failure: bool,
+    /// The snapshot taken before the last run.
+    pub(crate) last_snapshot: Option<Snapshot>,
+    /// The accepted tags (`None` to run every migration).
+    pub(crate) tags: Option<BTreeSet<String>>,
+    /// The repeatable migrations, run after the versioned ones.
+    pub(crate) repeatable_migrations: Vec<BoxRepeatable<MigContext>>,
+    /// What to do when the data is ahead of the latest known migration.
+    pub(crate) data_ahead: DataAhead,
+    /// The data is production data (the development operations are refused).
+    pub(crate) production: bool,
+    /// The version of the running application.
+    pub(crate) app_version: Option<String>,
+    /// What to do with data migrated by a newer version of the application.
+    pub(crate) app_version_policy: AppVersionPolicy,
 }
 
-impl<'m, 'c, MigContext, M: Metadata> Migratex<'m, 'c, MigContext, M> {
-    /// Create a new Migratex.
+/// OwnedMigratex is a Migratex owning its context and metadata (see `Migratex::owned`),
+/// e.g. to store it in the application state.
+pub type OwnedMigratex<MigContext, M, V = i32> = Migratex<'static, 'static, MigContext, M, V>;
+
+impl<'m, 'c, MigContext, M: Metadata<V>, V: Version> Migratex<'m, 'c, MigContext, M, V> {
+    /// Create a new Migratex, borrowing the context and the metadata.
     pub fn new(
         ctx: &'c mut MigContext,
         meta: &'m mut M,
-        migrations: Vec<BoxMigration<MigContext>>,
+        migrations: Vec<BoxMigration<MigContext, V>>,
+    ) -> Self {
+        Self::with_slots(Slot::Borrowed(ctx), Slot::Borrowed(meta), migrations)
+    }
+
+    /// Create a new Migratex, owning the context and the metadata
+    /// (get them back with `into_parts`).
+    pub fn owned(ctx: MigContext, meta: M, migrations: Vec<BoxMigration<MigContext, V>>) -> Self {
+        Self::with_slots(Slot::Owned(ctx), Slot::Owned(meta), migrations)
+    }
+
+    pub(crate) fn with_slots(
+        ctx: Slot<'c, MigContext>,
+        meta: Slot<'m, M>,
+        migrations: Vec<BoxMigration<MigContext, V>>,
     ) -> Self {
         Self {
             ctx,
             meta,
             migrations,
+            force_irreversible: false,
+            out_of_order: OutOfOrder::default(),
+            observers: Vec::new(),
+            run_timeout: None,
+            step_timeout: None,
+            cancellation: None,
+            retry: None,
+            backup: None,
+            restore_on_failure: false,
+            last_snapshot: None,
+            tags: None,
+            repeatable_migrations: Vec::new(),
+            data_ahead: DataAhead::default(),
+            production: false,
+            app_version: None,
+            app_version_policy: AppVersionPolicy::default(),
         }
     }
 
+    /// Allow (or not) a downgrade to cross irreversible migrations.
+    /// When forced, the `down` of an irreversible migration is still called.
+    pub fn with_force_irreversible(mut self, force: bool) -> Self {
+        self.force_irreversible = force;
+        self
+    }
+
+    /// Set what to do with the migrations merged out-of-order
+    /// (older than the current version, but never applied).
+    /// Default: `OutOfOrder::Error`.
+    pub fn with_out_of_order(mut self, out_of_order: OutOfOrder) -> Self {
+        self.out_of_order = out_of_order;
+        self
+    }
+
+    /// Set what to do when the data is ahead of the binary
+    /// (its version is greater than the latest known migration).
+    /// Default: `DataAhead::Error`.
+    pub fn with_data_ahead(mut self, data_ahead: DataAhead) -> Self {
+        self.data_ahead = data_ahead;
+        self
+    }
+
+    /// Flag (or not) the data as production data:
+    /// the development operations (`redo`, `redo_n`, `reset`) are refused
+    /// (`MigratexError::Production`).
+    pub fn with_production(mut self, production: bool) -> Self {
+        self.production = production;
+        self
+    }
+
+    /// Add an observer notified of the migration events
+    /// (before / after the run and each step, on error).
+    pub fn with_observer(mut self, observer: impl MigrationObserver<V> + 'static) -> Self {
+        self.observers.push(Box::new(observer));
+        self
+    }
+
+    /// Report the progress of the run to a `Progress` (per step and overall).
+    /// Put a clone of the same `Progress` in the migration context
+    /// so the migrations can report their own progress (`items done / total`, message).
+    pub fn with_progress(self, progress: Progress<V>) -> Self {
+        self.with_observer(progress)
+    }
+
+    /// Set the maximum duration of a run (`migrate_to` and co).
+    /// When elapsed, the run stops between two steps (or the running step times out).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.run_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the default maximum duration of a step (the `up` or `down` of a migration),
+    /// for the migrations without their own `Migration::timeout`.
+    /// A step timing out is interrupted and fails.
+    pub fn with_step_timeout(mut self, timeout: Duration) -> Self {
+        self.step_timeout = Some(timeout);
+        self
+    }
+
+    /// Stop the run between two steps when the token is cancelled
+    /// (e.g. on a shutdown signal). The metadata stays `Clean`,
+    /// at the version of the last completed step.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    /// Retry the steps failing with transient errors, according to a policy.
+    /// Only the migrations opting in (`Migration::retryable`) are retried.
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Snapshot the data with a backup before each run (when there is something to run).
+    /// If the snapshot fails, nothing is run.
+    pub fn with_backup(mut self, backup: impl Backup + 'static) -> Self {
+        self.backup = Some(Box::new(backup));
+        self
+    }
+
+    /// Restore (or not) the snapshot of the backup when a run fails.
+    /// When restored, the metadata is `Clean`, back at the version the run started from.
+    pub fn with_restore_on_failure(mut self, restore: bool) -> Self {
+        self.restore_on_failure = restore;
+        self
+    }
+
+    /// Accept only the migrations tagged with one of `tags` (see `Migration::tags`),
+    /// e.g. the environment (`"dev"`, `"prod"`) and the platform (`std::env::consts::OS`).
+    /// The migrations without tags always run, the other ones are skipped:
+    /// recorded as skipped (see `Metadata::skipped_versions`), the version still advances.
+    /// Default: no filter, every migration runs.
+    pub fn with_tags<T: Into<String>>(mut self, tags: impl IntoIterator<Item = T>) -> Self {
+        self.tags = Some(tags.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Set the repeatable migrations (see `RepeatableMigration`), run in order
+    /// after the versioned migrations when migrating up to the latest version,
+    /// each time its checksum differs from the stored one (see `Metadata::checksums`).
+    pub fn with_repeatable_migrations(
+        mut self,
+        migrations: Vec<BoxRepeatable<MigContext>>,
+    ) -> Self {
+        self.repeatable_migrations = migrations;
+        self
+    }
+
+    /// Set the version of the running application (e.g. `env!("CARGO_PKG_VERSION")` of the app),
+    /// recorded in the metadata (`app_version`) after each successful run.
+    /// The data migrated by a newer version of the application is refused
+    /// (depending on `with_app_version_policy`).
+    pub fn with_app_version(mut self, app_version: impl Into<String>) -> Self {
+        self.app_version = Some(app_version.into());
+        self
+    }
+
+    /// Set what to do with the data migrated by a newer version of the application.
+    /// Default: `AppVersionPolicy::Error`.
+    pub fn with_app_version_policy(mut self, policy: AppVersionPolicy) -> Self {
+        self.app_version_policy = policy;
+        self
+    }
+
+    /// Get the snapshot taken (by the backup) before the last run.
+    pub fn last_snapshot(&self) -> Option<&Snapshot> {
+        self.last_snapshot.as_ref()
+    }
+
     /// Get the current metadata.
     pub fn metadata(&self) -> &M {
-        self.meta
+        &self.meta
     }
 
     /// Get the migration context.
     pub fn context(&self) -> &MigContext {
-        self.ctx
+        &self.ctx
+    }
+
+    /// Give back the owned context and metadata (see `owned`),
+    /// e.g. to save the metadata after a run.
+    /// Each part is `None` when it is borrowed (see `new`, `MigratexBuilder`).
+    pub fn into_parts(self) -> (Option<MigContext>, Option<M>) {
+        (self.ctx.into_owned(), self.meta.into_owned())
     }
 
     /// Get the most recent migration version.
-    pub fn latest_version(&self) -> i32 {
-        self.migrations.last().map(|m| m.version()).unwrap_or(0)
+    pub fn latest_version(&self) -> V {
+        self.migrations
+            .iter()
+            .map(|m| m.version())
+            .max()
+            .unwrap_or_else(V::zero)
+    }
+
+    /// Get the versions of the applied migrations.
+    /// When the metadata does not track them (or has not tracked them yet),
+    /// every known migration up to the current version is considered applied.
+    pub fn applied_versions(&self) -> BTreeSet<V> {
+        match self.meta.applied_versions() {
+            Some(applied) if !applied.is_empty() || self.meta.version() == V::zero() => {
+                applied.clone()
+            }
+            _ => {
+                let current = self.meta.version();
+                let mut applied: BTreeSet<_> = self
+                    .migrations
+                    .iter()
+                    .map(|m| m.version())
+                    .filter(|v| *v <= current)
+                    .collect();
+
+                // The data is at the current version, even without a known migration for it
+                // (e.g. baseline)
+                if current > V::zero() {
+                    applied.insert(current);
+                }
+                applied
+            }
+        }
+    }
+
+    /// Get the versions of the migrations merged out-of-order:
+    /// older than the current version, but never applied.
+    pub fn missing_versions(&self) -> Vec<V> {
+        let current = self.meta.version();
+        let applied = self.applied_versions();
+        let mut missing: Vec<_> = self
+            .migrations
+            .iter()
+            .filter(|m| m.squashed_from().is_none())
+            .map(|m| m.version())
+            .filter(|v| *v < current && !applied.contains(v))
+            .collect();
+
+        missing.sort();
+        missing
     }
 
     /// Migrate from current metadata.version up to the latest migration version
@@ -59,87 +333,722 @@ impl<'m, 'c, MigContext, M: Metadata> Migratex<'m, 'c, MigContext, M> {
 
     /// Rollback everything (migrate to version 0, before the first migration).
     pub async fn migrate_to_zero(&mut self) -> Result<()> {
-        self.migrate_to(0).await
+        self.migrate_to(V::zero()).await
     }
 
     /// Migrate to the next version (up)
     pub async fn migrate_next(&mut self) -> Result<()> {
         let current = self.meta.version();
 
-        if current >= self.latest_version() {
-            return Ok(());
-        }
+        let next = self
+            .migrations
+            .iter()
+            .map(|m| m.version())
+            .filter(|v| *v > current)
+            .min();
 
-        self.migrate_to(current + 1).await
+        match next {
+            Some(target) => self.migrate_to(target).await,
+            None => Ok(()),
+        }
     }
 
     /// Migrate to the previous version (down)
     pub async fn migrate_prev(&mut self) -> Result<()> {
         let current = self.meta.version();
 
-        if current <= 0 {
+        if current <= V::zero() {
             return Ok(());
         }
 
-        self.migrate_to(current - 1).await
+        let target = self.version_before(&current, &self.applied_versions());
+        self.migrate_to(target).await
     }
 
-    /// Migrate to a specific target version (up or down)
-    pub async fn migrate_to(&mut self, target: i32) -> Result<()> {
+    /// Redo the current version: roll back its migration, then apply it again
+    /// (useful to test a migration during development).
+    pub async fn redo(&mut self) -> Result<()> {
+        self.redo_n(1).await
+    }
+
+    /// Redo the `n` last versions: roll back their migrations, then apply them again.
+    /// Refused on production data (see `with_production`).
+    pub async fn redo_n(&mut self, n: usize) -> Result<()> {
+        self.check_production("redo")?;
+
         let current = self.meta.version();
+        let applied = self.applied_versions();
+        let mut target = current.clone();
+
+        for _ in 0..n {
+            if target <= V::zero() {
+                break;
+            }
+            target = self.version_before(&target, &applied);
+        }
 
         if target == current {
             return Ok(());
         }
 
-        self.meta.mark_migrating();
+        self.migrate_to(target).await?;
+        self.migrate_to(current).await
+    }
+
+    /// Reset: roll back everything (to version 0), then migrate up to the latest version.
+    /// Refused on production data (see `with_production`).
+    pub async fn reset(&mut self) -> Result<()> {
+        self.check_production("reset")?;
+        self.migrate_to_zero().await?;
+        self.migrate_to_latest().await
+    }
+
+    /// Baseline: declare that the data is already at a given version, without running anything.
+    /// Useful to adopt Migratex on an existing system, or to start existing deployments
+    /// above the range of a squashed migration.
+    pub fn baseline(&mut self, version: V) {
+        let known: Vec<_> = self
+            .migrations
+            .iter()
+            .map(|m| m.version())
+            .filter(|v| *v <= version)
+            .collect();
+
+        if let Some(applied) = self.meta.applied_versions_mut() {
+            *applied = known.into_iter().collect();
+            if version > V::zero() {
+                applied.insert(version.clone());
+            }
+        }
+
+        self.meta.set_version(version);
+        self.meta.mark_clean();
+    }
+
+    /// Plan the steps to migrate from the current version to a specific target version (up or down).
+    /// Nothing is run, but the plan is refused if a downgrade crosses an irreversible migration
+    /// (unless forced, see `with_force_irreversible`),
+    /// or if migrations merged out-of-order are found (depending on `with_out_of_order`),
+    /// or if the data is ahead of the latest known migration (depending on `with_data_ahead`),
+    /// or if the data was migrated by a newer app version (depending on `with_app_version_policy`).
+    pub fn plan(&self, target: V) -> Result<MigrationPlan<V>> {
+        self.check_app_version()?;
+
+        let current = self.meta.version();
+        let latest = self.latest_version();
+
+        if current > latest && self.data_ahead == DataAhead::Error {
+            return Err(MigratexError::DataAhead {
+                version: current,
+                latest,
+            }
+            .into());
+        }
 
-        let result = if target > current {
-            // UP:  current+1 ..= target
-            self.migrate_up(current, target).await
+        let steps = if target >= current {
+            // UP:  current+1 ..= target (+ the missing ones)
+            self.plan_up(&current, &target)?
         } else {
             // DOWN: current ..> target
-            self.migrate_down(current, target).await
+            self.plan_down(&current, &target)?
         };
 
-        match result {
+        if !self.force_irreversible
+            && let Some(step) = steps
+                .iter()
+                .find(|s| s.direction == Direction::Down && !s.reversible && !s.skipped)
+        {
+            return Err(MigratexError::Irreversible {
+                version: step.version.clone(),
+                name: step.name.clone(),
+                target,
+            }
+            .into());
+        }
+
+        let repeatable = self.plan_repeatable(&current, &target);
+
+        Ok(MigrationPlan {
+            from: current,
+            target,
+            steps,
+            repeatable,
+        })
+    }
+
+    /// Migrate to a specific target version (up or down)
+    pub async fn migrate_to(&mut self, target: V) -> Result<()> {
+        let plan = self.plan(target)?;
+
+        if plan.is_empty() {
+            self.record_app_version();
+            return Ok(());
+        }
+
+        // Fill the applied set of a metadata not tracking it yet
+        let applied = self.applied_versions();
+        if let Some(meta_applied) = self.meta.applied_versions_mut()
+            && meta_applied.is_empty()
+        {
+            *meta_applied = applied;
+        }
+
+        if let Some(backup) = &self.backup {
+            self.last_snapshot = Some(backup.snapshot().await?);
+        }
+
+        let sets_before = MetaSets::save(&*self.meta);
+        self.meta.mark_migrating();
+
+        let started = Instant::now();
+        self.observers.iter().for_each(|o| o.before_run(&plan));
+
+        // No deadline when too far to be represented
+        let deadline = self.run_timeout.and_then(|t| started.checked_add(t));
+        let span = TraceSpan::run(&plan);
+        let result = span.instrument(self.run_plan(&plan, deadline)).await;
+        span.record_outcome(&result);
+
+        match &result {
             Ok(()) => {
                 self.meta.mark_clean();
-                Ok(())
+                self.record_app_version();
             }
-            Err(e) => {
+            // Stopped between two steps, the data is at the version of the last completed step
+            Err(e) if is_stopped(e.downcast_ref::<MigratexError<V>>()) => self.meta.mark_clean(),
+            Err(_) if self.restore(&plan, sets_before).await => {}
+            Err(_) => {
                 self.meta.mark_failed();
-                Err(e)
+                trace_event!(
+                    warn,
+                    version = %self.meta.version(),
+                    "migratex metadata marked failed"
+                );
             }
         }
+
+        self.observers
+            .iter()
+            .for_each(|o| o.after_run(&plan, &result, started.elapsed()));
+
+        result
     }
 
-    /// Migrate up to a specific target version.
-    async fn migrate_up(&mut self, current: i32, target: i32) -> Result<()> {
-        for m in &self.migrations {
-            let v = m.version();
-            if v > current && v <= target {
-                m.up(self.ctx).await?;
-                self.meta.set_version(v);
+    /// Refuse a development operation on production data (see `with_production`).
+    fn check_production(&self, operation: &str) -> Result<()> {
+        if self.production {
+            return Err(MigratexError::<V>::Production {
+                operation: operation.to_string(),
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Check that the data was not migrated by a newer version of the application
+    /// (see `with_app_version_policy`). The versions that cannot be compared are accepted.
+    fn check_app_version(&self) -> Result<()> {
+        let Some(app_version) = &self.app_version else {
+            return Ok(());
+        };
+
+        // Legacy value (the version of migratex), not recorded by the application
+        if !self.meta.app_version_recorded() {
+            return Ok(());
+        }
+
+        let stored = self.meta.app_version();
+
+        if self.app_version_policy == AppVersionPolicy::Error
+            && cmp_app_versions(stored, app_version) == Some(std::cmp::Ordering::Greater)
+        {
+            return Err(MigratexError::<V>::NewerAppVersion {
+                app_version: app_version.clone(),
+                stored: stored.to_string(),
             }
+            .into());
         }
+
         Ok(())
     }
 
-    /// Migrate down to a specific target version.
-    async fn migrate_down(&mut self, current: i32, target: i32) -> Result<()> {
-        let mut sorted: Vec<_> = self.migrations.iter().collect();
-        sorted.sort_by_key(|m| m.version());
+    /// Record the version of the running application (if any) in the metadata.
+    fn record_app_version(&mut self) {
+        if let Some(app_version) = &self.app_version
+            && (self.meta.app_version() != app_version || !self.meta.app_version_recorded())
+        {
+            self.meta.set_app_version(app_version.clone());
+        }
+    }
+
+    /// Restore the snapshot of the backup after a failed run (if enabled),
+    /// and put the metadata back as it was when the run started (version and sets).
+    /// Returns whether the snapshot was restored.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    async fn restore(&mut self, plan: &MigrationPlan<V>, sets: MetaSets<V>) -> bool {
+        let (Some(backup), Some(snapshot)) = (&self.backup, &self.last_snapshot) else {
+            return false;
+        };
+
+        if !self.restore_on_failure {
+            return false;
+        }
+
+        if let Err(e) = backup.restore(snapshot).await {
+            trace_event!(error, error = %e, "migratex backup snapshot not restored");
+            return false;
+        }
+
+        sets.put_back(&mut *self.meta);
+        self.meta.set_version(plan.from.clone());
+        self.meta.mark_clean();
+        true
+    }
+
+    /// Run the steps of a plan, in order, until the run deadline (if any).
+    async fn run_plan(&mut self, plan: &MigrationPlan<V>, deadline: Option<Instant>) -> Result<()> {
+        for step in &plan.steps {
+            self.check_stop(deadline)?;
+
+            let started = Instant::now();
+            self.observers.iter().for_each(|o| o.before_step(step));
+
+            let result = if step.skipped {
+                trace_event!(info, version = %step.version, "migratex migration step skipped");
+                Ok(())
+            } else {
+                let span = TraceSpan::step(step);
+                let result = span.instrument(self.run_step(step, deadline)).await;
+                span.record_outcome(&result);
+                result
+            };
+
+            if let Err(e) = result {
+                self.observers
+                    .iter()
+                    .for_each(|o| o.on_error(step, &e, started.elapsed()));
+                return Err(e);
+            }
+
+            match step.direction {
+                Direction::Up => self.meta.mark_applied(step.version.clone()),
+                Direction::Down => self.meta.mark_unapplied(step.version.clone()),
+            }
+
+            // A squashed migration rolled back also rolls back the versions it replaces
+            if step.direction == Direction::Down
+                && let Some(from) = self.migrations[step.index].squashed_from()
+            {
+                let replaced = |v: &V| *v > from && *v < step.version;
+                if let Some(applied) = self.meta.applied_versions_mut() {
+                    applied.retain(|v| !replaced(v));
+                }
+                if let Some(skipped) = self.meta.skipped_versions_mut() {
+                    skipped.retain(|v| !replaced(v));
+                }
+            }
+
+            match (step.direction, step.skipped) {
+                (Direction::Up, true) => self.meta.mark_skipped(step.version.clone()),
+                (Direction::Down, _) => self.meta.mark_unskipped(step.version.clone()),
+                (Direction::Up, false) => {}
+            }
+
+            self.meta.set_version(step.resulting_version.clone());
+
+            self.observers
+                .iter()
+                .for_each(|o| o.after_step(step, started.elapsed()));
+        }
+
+        for name in &plan.repeatable {
+            self.check_stop(deadline)?;
+            self.run_repeatable(name)
+                .await
+                .with_context(|| format!("repeatable migration {name}"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Run a repeatable migration, then store its checksum.
+    async fn run_repeatable(&mut self, name: &str) -> Result<()> {
+        let Some(m) = self.repeatable_migrations.iter().find(|m| m.name() == name) else {
+            return Ok(());
+        };
+
+        let checksum = m.checksum();
+        m.run(&mut self.ctx).await?;
+
+        trace_event!(info, name, checksum = %checksum, "migratex repeatable migration applied");
+        self.meta.set_checksum(name.to_string(), checksum);
+        Ok(())
+    }
+
+    /// Check whether the run must stop (before a step): cancelled or run deadline elapsed.
+    fn check_stop(&self, deadline: Option<Instant>) -> Result<()> {
+        let version = self.meta.version();
+
+        if self.cancellation.as_ref().is_some_and(|t| t.is_cancelled()) {
+            return Err(MigratexError::Cancelled { version }.into());
+        }
+
+        if let (Some(deadline), Some(timeout)) = (deadline, self.run_timeout)
+            && Instant::now() >= deadline
+        {
+            return Err(MigratexError::RunTimeout { timeout, version }.into());
+        }
+
+        Ok(())
+    }
+
+    /// Run a step, retrying it on transient errors (see `with_retry`).
+    async fn run_step(&mut self, step: &PlanStep<V>, deadline: Option<Instant>) -> Result<()> {
+        let retryable = self.migrations[step.index].retryable();
+        let mut attempt = 1;
+
+        loop {
+            let error = match self.run_attempt(step, deadline).await {
+                Ok(()) => return Ok(()),
+                Err(e) => e,
+            };
+
+            let delay = match &self.retry {
+                Some(policy)
+                    if retryable
+                        && attempt < policy.max_attempts()
+                        && !is_condition_failure(error.downcast_ref::<MigratexError<V>>())
+                        && policy.is_transient(&error) =>
+                {
+                    policy.backoff().delay(attempt)
+                }
+                _ => return Err(error),
+            };
+
+            // Do not retry a cancelled run, or past the run deadline
+            if self.cancellation.as_ref().is_some_and(|t| t.is_cancelled())
+                || deadline.is_some_and(|d| {
+                    Instant::now()
+                        .checked_add(delay)
+                        .is_none_or(|retry_at| retry_at >= d)
+                })
+            {
+                return Err(error);
+            }
+
+            trace_event!(warn, attempt, error = %error, "migratex migration step retried");
+            self.observers
+                .iter()
+                .for_each(|o| o.on_retry(step, &error, attempt));
+
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    /// Run an attempt of a step: the `up` or `down` of its migration
+    /// between its pre- and post-conditions, bounded by its timeout and the run deadline.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    async fn run_attempt(&mut self, step: &PlanStep<V>, deadline: Option<Instant>) -> Result<()> {
+        let m = &self.migrations[step.index];
+
+        let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+        let limit = match (m.timeout().or(self.step_timeout), remaining) {
+            (Some(t), Some(r)) => Some(t.min(r)),
+            (t, r) => t.or(r),
+        };
+
+        let run = async {
+            if let Err(e) = m.check_before(&mut self.ctx, step.direction).await {
+                return Err(MigratexError::CheckFailed {
+                    version: step.version.clone(),
+                    name: step.name.clone(),
+                    reason: format!("{:#}", e),
+                }
+                .into());
+            }
+
+            match step.direction {
+                Direction::Up => m.up(&mut self.ctx).await?,
+                Direction::Down => m.down(&mut self.ctx).await?,
+            }
+
+            let Err(e) = m.verify_after(&mut self.ctx, step.direction).await else {
+                return Ok(());
+            };
+
+            // Undo the step (when possible), so the data stays at the previous version
+            let undo = match step.direction {
+                Direction::Up if m.reversible() => Some(m.down(&mut self.ctx).await),
+                Direction::Up => None,
+                Direction::Down => Some(m.up(&mut self.ctx).await),
+            };
+
+            if let Some(Err(undo_error)) = &undo {
+                trace_event!(
+                    warn,
+                    error = %undo_error,
+                    "migratex migration step not rolled back after a failed verification"
+                );
+            }
+
+            Err(MigratexError::VerifyFailed {
+                version: step.version.clone(),
+                name: step.name.clone(),
+                reason: format!("{:#}", e),
+                rolled_back: matches!(undo, Some(Ok(()))),
+            }
+            .into())
+        };
+
+        match timeout(limit, run).await {
+            Some(result) => result,
+            None => Err(MigratexError::Timeout {
+                version: step.version.clone(),
+                name: step.name.clone(),
+                timeout: limit.unwrap_or_default(),
+            }
+            .into()),
+        }
+    }
+
+    /// Get the version of the data once the migration `v` is rolled back:
+    /// the version a squashed migration starts from, or the highest applied version below `v`,
+    /// or zero.
+    fn version_before(&self, v: &V, applied: &BTreeSet<V>) -> V {
+        self.migrations
+            .iter()
+            .find(|m| m.version() == *v)
+            .and_then(|m| m.squashed_from())
+            .or_else(|| applied.range(..v).next_back().cloned())
+            .unwrap_or_else(V::zero)
+    }
+
+    /// Whether a migration is accepted by the tag filter (if any).
+    fn accepts(&self, m: &dyn Migration<MigContext, V>) -> bool {
+        match &self.tags {
+            Some(accepted) => m.tags().is_empty() || m.tags().iter().any(|t| accepted.contains(*t)),
+            None => true,
+        }
+    }
+
+    /// Get the names of the repeatable migrations to run (their checksum changed),
+    /// only when migrating up to the latest version.
+    fn plan_repeatable(&self, current: &V, target: &V) -> Vec<String> {
+        if target < current || *target < self.latest_version() {
+            return Vec::new();
+        }
+
+        let checksums = self.meta.checksums();
+
+        self.repeatable_migrations
+            .iter()
+            .filter(|m| checksums.and_then(|c| c.get(m.name())) != Some(&m.checksum()))
+            .map(|m| m.name().to_string())
+            .collect()
+    }
+
+    /// Plan the steps to migrate up to a specific target version.
+    fn plan_up(&self, current: &V, target: &V) -> Result<Vec<PlanStep<V>>> {
+        for m in &self.migrations {
+            if let Some(from) = m.squashed_from() {
+                let v = m.version();
+                // Partially migrated with the old (squashed) migrations
+                let inside = if *current > from && *current < v {
+                    Some(current.clone())
+                // Cannot stop in the middle of a squashed migration
+                } else if *current <= from && *target > from && *target < v {
+                    Some(target.clone())
+                } else {
+                    None
+                };
+
+                if let Some(at) = inside {
+                    return Err(squash_error(m.as_ref(), from, at));
+                }
+            }
+        }
+
+        let missing = self.missing_versions();
+
+        if !missing.is_empty() && self.out_of_order == OutOfOrder::Error {
+            return Err(MigratexError::OutOfOrder {
+                versions: missing,
+                current: current.clone(),
+            }
+            .into());
+        }
+
+        let mut steps: Vec<_> = self
+            .migrations
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| {
+                let v = m.version();
+                (v > *current && v <= *target)
+                    || (self.out_of_order == OutOfOrder::Apply && missing.contains(&v))
+            })
+            .map(|(index, m)| PlanStep {
+                version: m.version(),
+                name: m.name().to_string(),
+                direction: Direction::Up,
+                // A missing migration does not change the current version
+                resulting_version: m.version().max(current.clone()),
+                reversible: m.reversible(),
+                depends_on: m.depends_on(),
+                skipped: !self.accepts(m.as_ref()),
+                index,
+            })
+            .collect();
+
+        steps.sort_by(|a, b| a.version.cmp(&b.version));
+        Ok(steps)
+    }
+
+    /// Plan the steps to migrate down to a specific target version.
+    fn plan_down(&self, current: &V, target: &V) -> Result<Vec<PlanStep<V>>> {
+        for m in &self.migrations {
+            if let Some(from) = m.squashed_from() {
+                let v = m.version();
+                // Cannot stop in the middle of a squashed migration
+                if *current >= v && *target > from && *target < v {
+                    return Err(squash_error(m.as_ref(), from, target.clone()));
+                }
+            }
+        }
+
+        let mut applied = self.applied_versions();
+        let mut sorted: Vec<_> = self.migrations.iter().enumerate().collect();
+        sorted.sort_by_key(|(_, m)| m.version());
         sorted.reverse(); // highest → lowest
 
-        for m in sorted {
+        let mut steps = Vec::new();
+
+        for (index, m) in sorted {
             let v = m.version();
-            if v <= current && v > target {
-                m.down(self.ctx).await?;
-                // convention: after down of v, highest applied = v - 1
-                self.meta.set_version(v - 1);
+            if v <= *current && v > *target && applied.contains(&v) {
+                applied.remove(&v);
+                // A squashed migration also rolls back the versions it replaces
+                if let Some(from) = m.squashed_from() {
+                    applied.retain(|a| *a <= from || *a > v);
+                }
+                steps.push(PlanStep {
+                    resulting_version: self.version_before(&v, &applied).max(target.clone()),
+                    skipped: self.meta.skipped_versions().is_some_and(|s| s.contains(&v)),
+                    version: v,
+                    name: m.name().to_string(),
+                    direction: Direction::Down,
+                    reversible: m.reversible(),
+                    depends_on: m.depends_on(),
+                    index,
+                });
             }
         }
-        Ok(())
+
+        Ok(steps)
+    }
+}
+
+/// The sets of the metadata as they were before a run,
+/// put back when the snapshot of the backup is restored.
+struct MetaSets<V> {
+    applied: Option<BTreeSet<V>>,
+    skipped: Option<BTreeSet<V>>,
+    checksums: Option<BTreeMap<String, String>>,
+}
+
+impl<V: Version> MetaSets<V> {
+    /// Save the sets of the metadata (if tracked).
+    fn save(meta: &impl Metadata<V>) -> Self {
+        Self {
+            applied: meta.applied_versions().cloned(),
+            skipped: meta.skipped_versions().cloned(),
+            checksums: meta.checksums().cloned(),
+        }
+    }
+
+    /// Put back the saved sets in the metadata.
+    fn put_back(self, meta: &mut impl Metadata<V>) {
+        if let (Some(current), Some(saved)) = (meta.applied_versions_mut(), self.applied) {
+            *current = saved;
+        }
+        if let (Some(current), Some(saved)) = (meta.skipped_versions_mut(), self.skipped) {
+            *current = saved;
+        }
+        if let (Some(current), Some(saved)) = (meta.checksums_mut(), self.checksums) {
+            *current = saved;
+        }
+    }
+}
+
+/// Whether the error stopped the run between two steps (cancelled or run timeout).
+fn is_stopped<V: Version>(error: Option<&MigratexError<V>>) -> bool {
+    matches!(
+        error,
+        Some(MigratexError::Cancelled { .. } | MigratexError::RunTimeout { .. })
+    )
+}
+
+/// Whether the error is a failed pre- or post-condition (never retried).
+fn is_condition_failure<V: Version>(error: Option<&MigratexError<V>>) -> bool {
+    matches!(
+        error,
+        Some(MigratexError::CheckFailed { .. } | MigratexError::VerifyFailed { .. })
+    )
+}
+
+/// Error for a version inside the range replaced by a squashed migration.
+fn squash_error<MigContext, V: Version>(
+    m: &dyn Migration<MigContext, V>,
+    from: V,
+    at: V,
+) -> okerr::Error {
+    MigratexError::InsideSquash {
+        version: m.version(),
+        name: m.name().to_string(),
+        from,
+        at,
+    }
+    .into()
+}
+
+/// A value borrowed or owned by Migratex (the context, the metadata).
+/// `Missing` until given to the builder (never in a built Migratex, see `MigratexBuilder::build`).
+pub(crate) enum Slot<'a, T> {
+    Borrowed(&'a mut T),
+    Owned(T),
+    Missing,
+}
+
+impl<T> Slot<'_, T> {
+    /// Get the owned value, `None` when borrowed.
+    fn into_owned(self) -> Option<T> {
+        match self {
+            Slot::Borrowed(_) | Slot::Missing => None,
+            Slot::Owned(value) => Some(value),
+        }
+    }
+}
+
+impl<T> Deref for Slot<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match self {
+            Slot::Borrowed(value) => value,
+            Slot::Owned(value) => value,
+            Slot::Missing => unreachable!("missing slot (checked by MigratexBuilder::build)"),
+        }
+    }
+}
+
+impl<T> DerefMut for Slot<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        match self {
+            Slot::Borrowed(value) => value,
+            Slot::Owned(value) => value,
+            Slot::Missing => unreachable!("missing slot (checked by MigratexBuilder::build)"),
+        }
     }
 }
@@ -5,23 +5,124 @@
 // information and documentation: https://github.com/nicolab/migratex
 // -----------------------------------------------------------------------------
 
-use okerr::Result;
+use std::time::Duration;
+
 use async_trait::async_trait;
+use okerr::Result;
+
+use crate::{Direction, Version};
 
 /// A migration is a version of a change in the data.
 /// It can be a database migration, a file migration, a binary migration, etc.
 /// It can be up (upgrade) or down (downgrade / rollback).
+/// The version type `V` is `i32` by default (see [`Version`]).
 #[async_trait]
-pub trait Migration<MigContext>: Send + Sync {
+pub trait Migration<MigContext, V: Version = i32>: Send + Sync {
     /// The version of the migration.
-    fn version(&self) -> i32;
+    fn version(&self) -> V;
+
+    /// The name of the migration.
+    /// By default, it is the type name of the migration.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
 
     /// Upgrade the data to the `version` of the migration.
     async fn up(&self, ctx: &mut MigContext) -> Result<()>;
 
     /// Downgrade (rollback) the data. Think of it as a rollback / cancel of the current migration.
     async fn down(&self, ctx: &mut MigContext) -> Result<()>;
+
+    /// Pre-condition checked before the `up` or `down` (`direction`) of the migration
+    /// (e.g. assert row counts or file invariants).
+    /// If it fails, nothing is run and the step fails.
+    /// By default, no check.
+    async fn check_before(&self, _ctx: &mut MigContext, _direction: Direction) -> Result<()> {
+        Ok(())
+    }
+
+    /// Post-condition verified after the `up` or `down` (`direction`) of the migration,
+    /// instead of trusting that it did the right thing.
+    /// If it fails, the step is undone when possible (`down` after a reversible `up`,
+    /// `up` after a `down`) and the step fails.
+    /// By default, no verification.
+    async fn verify_after(&self, _ctx: &mut MigContext, _direction: Direction) -> Result<()> {
+        Ok(())
+    }
+
+    /// Whether the migration can be undone.
+    /// An irreversible migration (dropping data, lossy transformation, etc)
+    /// blocks any downgrade crossing it, unless forced.
+    /// By default, a migration is reversible.
+    fn reversible(&self) -> bool {
+        true
+    }
+
+    /// For a squashed migration (replacing the old migrations `from + 1 ..= version`),
+    /// the version it starts from (exclusive), usually `0` (`Version::zero`).
+    /// Fresh installs (at `from` or below) run the squashed migration,
+    /// installs already at `version` or above skip it.
+    /// By default, a migration is not a squash (`None`).
+    fn squashed_from(&self) -> Option<V> {
+        None
+    }
+
+    /// The maximum duration of the `up` or `down` of the migration,
+    /// overriding the default step timeout of `Migratex` (see `with_step_timeout`).
+    /// A step timing out is interrupted and fails.
+    /// By default, no timeout (`None`).
+    fn timeout(&self) -> Option<Duration> {
+        None
+    }
+
+    /// Whether the `up` / `down` of the migration can be retried on transient errors
+    /// (idempotent steps only), according to the retry policy of `Migratex` (see `with_retry`).
+    /// By default, a migration is not retried.
+    fn retryable(&self) -> bool {
+        false
+    }
+
+    /// The migrations (of other tracks) this migration requires first,
+    /// e.g. a plugin migration requiring a given core version.
+    /// `Tracks` orders the steps of its tracks accordingly (see `Tracks::schedule_latest`).
+    /// By default, no dependency.
+    fn depends_on(&self) -> Vec<Dependency<V>> {
+        Vec::new()
+    }
+
+    /// The tags of the migration (e.g. an environment like `"dev"` / `"prod"`, a platform, etc).
+    /// When `Migratex` has a tag filter (see `with_tags`), a tagged migration runs
+    /// only if one of its tags is accepted, otherwise it is skipped (recorded as skipped).
+    /// By default, no tag: the migration always runs.
+    fn tags(&self) -> &[&str] {
+        &[]
+    }
+}
+
+/// A dependency of a migration: a track must be at `version` (or above) first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency<V: Version = i32> {
+    /// The name of the track (see `Tracks::with_track`).
+    pub track: String,
+    /// The version the track must be at (or above).
+    pub version: V,
+}
+
+impl<V: Version> Dependency<V> {
+    /// Create a new Dependency on `track` at `version` (or above).
+    pub fn new(track: impl Into<String>, version: V) -> Self {
+        Self {
+            track: track.into(),
+            version,
+        }
+    }
+}
+
+impl<V: Version> std::fmt::Display for Dependency<V> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.track, self.version)
+    }
 }
 
 /// BoxMigration is the type of a migration.
-pub type BoxMigration<MigContext> = Box<dyn Migration<MigContext> + Send + Sync>;
+pub type BoxMigration<MigContext, V = i32> = Box<dyn Migration<MigContext, V> + Send + Sync>;
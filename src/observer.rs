@@ -0,0 +1,65 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use okerr::{Error, Result};
+
+use crate::{MigrationPlan, PlanStep, Version};
+
+/// MigrationObserver is notified of the migration events of a `Migratex` run.
+/// Useful to plug logging, metrics, progress bars, notifications, etc
+/// without editing every migration.
+/// All the methods have a default (no-op) implementation, implement only the needed ones.
+pub trait MigrationObserver<V: Version = i32>: Send + Sync {
+    /// Called before running a plan (not called when there is nothing to run).
+    fn before_run(&self, _plan: &MigrationPlan<V>) {}
+
+    /// Called before running a step (the `up` or `down` of a migration).
+    fn before_step(&self, _step: &PlanStep<V>) {}
+
+    /// Called after a step succeeded.
+    fn after_step(&self, _step: &PlanStep<V>, _duration: Duration) {}
+
+    /// Called when a step failed.
+    fn on_error(&self, _step: &PlanStep<V>, _error: &Error, _duration: Duration) {}
+
+    /// Called when a failed step is about to be retried (see `Migratex::with_retry`),
+    /// `attempt` being the number of the failed attempt (1 for the first one).
+    fn on_retry(&self, _step: &PlanStep<V>, _error: &Error, _attempt: u32) {}
+
+    /// Called after running a plan, with the result of the run.
+    fn after_run(&self, _plan: &MigrationPlan<V>, _result: &Result<()>, _duration: Duration) {}
+}
+
+/// A shared observer, so the caller can keep a handle on it (e.g. to read collected metrics).
+impl<V: Version, O: MigrationObserver<V>> MigrationObserver<V> for Arc<O> {
+    fn before_run(&self, plan: &MigrationPlan<V>) {
+        (**self).before_run(plan)
+    }
+
+    fn before_step(&self, step: &PlanStep<V>) {
+        (**self).before_step(step)
+    }
+
+    fn after_step(&self, step: &PlanStep<V>, duration: Duration) {
+        (**self).after_step(step, duration)
+    }
+
+    fn on_error(&self, step: &PlanStep<V>, error: &Error, duration: Duration) {
+        (**self).on_error(step, error, duration)
+    }
+
+    fn on_retry(&self, step: &PlanStep<V>, error: &Error, attempt: u32) {
+        (**self).on_retry(step, error, attempt)
+    }
+
+    fn after_run(&self, plan: &MigrationPlan<V>, result: &Result<()>, duration: Duration) {
+        (**self).after_run(plan, result, duration)
+    }
+}
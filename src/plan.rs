@@ -0,0 +1,118 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+use crate::{Dependency, Version};
+
+/// The direction of a migration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Upgrade.
+    Up,
+    /// Downgrade (rollback).
+    Down,
+}
+
+/// What to do with the migrations merged out-of-order:
+/// older than the current version, but never applied
+/// (e.g. two branches adding the versions 14 and 15, with 15 deployed first).
+/// It requires a metadata tracking the applied versions (see `Metadata::applied_versions`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutOfOrder {
+    /// Refuse to migrate up, reporting the missing versions (`MigratexError::OutOfOrder`).
+    #[default]
+    Error,
+    /// Apply the missing migrations (in ascending order), before the pending ones.
+    Apply,
+    /// Ignore the missing migrations (they are never applied).
+    Ignore,
+}
+
+/// What to do when the data is ahead of the binary: its version is greater than
+/// the latest known migration (e.g. migrated by a newer release, then the binary rolled back).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DataAhead {
+    /// Refuse to run (`MigratexError::DataAhead`).
+    #[default]
+    Error,
+    /// Ignore the unknown versions: nothing runs above the latest known migration,
+    /// the data stays at its version.
+    Ignore,
+}
+
+/// What to do when the data was migrated by a newer version of the application
+/// (the `app_version` recorded in the metadata is greater than the running one),
+/// e.g. after rolling back the binary.
+/// It requires the running application version (see `Migratex::with_app_version`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AppVersionPolicy {
+    /// Refuse to run (`MigratexError::NewerAppVersion`).
+    #[default]
+    Error,
+    /// Run anyway.
+    Ignore,
+}
+
+/// A step of a [`MigrationPlan`]: one migration to run in one direction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanStep<V: Version = i32> {
+    /// The version of the migration.
+    pub version: V,
+    /// The name of the migration.
+    pub name: String,
+    /// The direction of the step.
+    pub direction: Direction,
+    /// The metadata version once the step is done.
+    pub resulting_version: V,
+    /// Whether the migration is reversible.
+    pub reversible: bool,
+    /// The dependencies of the migration (see `Migration::depends_on`).
+    pub depends_on: Vec<Dependency<V>>,
+    /// Whether the step is skipped (not run, see `Migration::tags`):
+    /// the version advances (or goes back) without running the migration.
+    pub skipped: bool,
+    /// Index of the migration in the migrations list.
+    pub(crate) index: usize,
+}
+
+/// MigrationPlan is the list of the steps to run to migrate
+/// from the current version to a target version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationPlan<V: Version = i32> {
+    /// The current version.
+    pub from: V,
+    /// The requested target version.
+    pub target: V,
+    /// The steps to run, in order.
+    pub steps: Vec<PlanStep<V>>,
+    /// The names of the repeatable migrations to run after the steps
+    /// (their checksum changed), in order.
+    pub repeatable: Vec<String>,
+}
+
+impl<V: Version> MigrationPlan<V> {
+    /// Whether there is nothing to run.
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty() && self.repeatable.is_empty()
+    }
+
+    /// The direction of the plan.
+    pub fn direction(&self) -> Direction {
+        if self.target < self.from {
+            Direction::Down
+        } else {
+            Direction::Up
+        }
+    }
+
+    /// The metadata version once the plan is done.
+    pub fn resulting_version(&self) -> V {
+        self.steps
+            .last()
+            .map(|s| s.resulting_version.clone())
+            .unwrap_or_else(|| self.from.clone())
+    }
+}
@@ -0,0 +1,249 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+use std::sync::mpsc::{Receiver, channel};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use okerr::Result;
+
+use crate::{Direction, MigrationObserver, MigrationPlan, PlanStep, Version};
+
+/// A progress update of the running step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressUpdate<V: Version = i32> {
+    /// The version of the running migration.
+    pub version: V,
+    /// The name of the running migration.
+    pub name: String,
+    /// The direction of the running step.
+    pub direction: Direction,
+    /// The position of the running step in the run (1-based).
+    pub step: usize,
+    /// The number of steps of the run.
+    pub steps: usize,
+    /// The items done by the running step.
+    pub done: u64,
+    /// The total of items of the running step, when known.
+    pub total: Option<u64>,
+    /// The last message reported by the running step.
+    pub message: Option<String>,
+    /// The running step is finished (succeeded).
+    pub finished: bool,
+}
+
+impl<V: Version> ProgressUpdate<V> {
+    /// Get the progress of the running step (from 0.0 to 1.0), when its total is known.
+    pub fn step_fraction(&self) -> Option<f64> {
+        if self.finished {
+            return Some(1.0);
+        }
+
+        match self.total {
+            Some(0) => Some(1.0),
+            Some(total) => Some((self.done.min(total) as f64) / (total as f64)),
+            None => None,
+        }
+    }
+
+    /// Get the overall progress of the run (from 0.0 to 1.0).
+    /// A step without known total counts as not started until it is finished.
+    pub fn overall_fraction(&self) -> f64 {
+        if self.steps == 0 {
+            return 1.0;
+        }
+
+        let done_steps = self.step.saturating_sub(1) as f64;
+        (done_steps + self.step_fraction().unwrap_or(0.0)) / (self.steps as f64)
+    }
+}
+
+/// A progress listener (callback).
+type Listener<V> = Arc<dyn Fn(&ProgressUpdate<V>) + Send + Sync>;
+
+/// Progress state of the run.
+struct ProgressState<V: Version> {
+    steps: usize,
+    current: Option<ProgressUpdate<V>>,
+}
+
+/// Progress shared between `Migratex`, the migrations and the listeners.
+struct ProgressInner<V: Version> {
+    state: Mutex<ProgressState<V>>,
+    listeners: Mutex<Vec<Listener<V>>>,
+}
+
+/// Progress is a side channel to report the progress of long-running migrations
+/// (`items done / total`, message) and to follow it from a CLI or a UI.
+///
+/// It is a cheap handle (clone it): give it to `Migratex` with `with_progress`,
+/// put a clone in the migration context so the migrations can report their progress,
+/// and listen to the updates with `on_update` (callback) or `subscribe` (channel).
+///
+/// Example:
+///
+/// ```rust
+/// use migratex::Progress;
+///
+/// let progress = Progress::<i32>::new();
+///
+/// progress.on_update(|u| {
+///     println!("[{}/{}] {} {}/{:?}", u.step, u.steps, u.name, u.done, u.total);
+/// });
+///
+/// // In a migration (with the progress in its context)
+/// progress.set_total(1000);
+/// progress.inc(100);
+/// progress.message("Rewriting the files");
+/// ```
+pub struct Progress<V: Version = i32> {
+    inner: Arc<ProgressInner<V>>,
+}
+
+impl<V: Version> Clone for Progress<V> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Arc::clone(&self.inner),
+        }
+    }
+}
+
+impl<V: Version> Default for Progress<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: Version> Progress<V> {
+    /// Create a new Progress.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(ProgressInner {
+                state: Mutex::new(ProgressState {
+                    steps: 0,
+                    current: None,
+                }),
+                listeners: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Call a listener on each progress update.
+    pub fn on_update(&self, listener: impl Fn(&ProgressUpdate<V>) + Send + Sync + 'static) {
+        self.inner
+            .listeners
+            .lock()
+            .unwrap()
+            .push(Arc::new(listener));
+    }
+
+    /// Get a channel receiving each progress update (e.g. to render them from another thread).
+    pub fn subscribe(&self) -> Receiver<ProgressUpdate<V>> {
+        let (tx, rx) = channel();
+        self.on_update(move |u| {
+            // The receiver may be dropped, the updates are then discarded
+            let _ = tx.send(u.clone());
+        });
+        rx
+    }
+
+    /// Get the last progress update of the running step (None outside a step).
+    pub fn current(&self) -> Option<ProgressUpdate<V>> {
+        self.inner.state.lock().unwrap().current.clone()
+    }
+
+    /// Set the total of items of the running step.
+    pub fn set_total(&self, total: u64) {
+        self.update(|u| u.total = Some(total));
+    }
+
+    /// Set the items done by the running step.
+    pub fn set_done(&self, done: u64) {
+        self.update(|u| u.done = done);
+    }
+
+    /// Increment the items done by the running step.
+    pub fn inc(&self, n: u64) {
+        self.update(|u| u.done = u.done.saturating_add(n));
+    }
+
+    /// Report a message of the running step.
+    pub fn message(&self, message: impl Into<String>) {
+        let message = message.into();
+        self.update(|u| u.message = Some(message));
+    }
+
+    /// Update the running step and notify the listeners.
+    /// Ignored outside a step.
+    fn update(&self, f: impl FnOnce(&mut ProgressUpdate<V>)) {
+        let update = {
+            let mut state = self.inner.state.lock().unwrap();
+            match state.current.as_mut() {
+                Some(current) => {
+                    f(current);
+                    current.clone()
+                }
+                None => return,
+            }
+        };
+
+        self.notify(&update);
+    }
+
+    /// Notify the listeners (outside the locks,
+    /// so a listener can use the progress or add another listener).
+    fn notify(&self, update: &ProgressUpdate<V>) {
+        let listeners = self.inner.listeners.lock().unwrap().clone();
+        for listener in listeners {
+            listener(update);
+        }
+    }
+}
+
+/// `Migratex` drives the progress through the observer events.
+impl<V: Version> MigrationObserver<V> for Progress<V> {
+    fn before_run(&self, plan: &MigrationPlan<V>) {
+        let mut state = self.inner.state.lock().unwrap();
+        state.steps = plan.steps.len();
+        state.current = None;
+    }
+
+    fn before_step(&self, step: &PlanStep<V>) {
+        let update = {
+            let mut state = self.inner.state.lock().unwrap();
+            let position = state.current.as_ref().map_or(1, |u| u.step + 1);
+            let update = ProgressUpdate {
+                version: step.version.clone(),
+                name: step.name.clone(),
+                direction: step.direction,
+                step: position,
+                steps: state.steps,
+                done: 0,
+                total: None,
+                message: None,
+                finished: false,
+            };
+            state.current = Some(update.clone());
+            update
+        };
+
+        self.notify(&update);
+    }
+
+    fn after_step(&self, _step: &PlanStep<V>, _duration: Duration) {
+        self.update(|u| {
+            if let Some(total) = u.total {
+                u.done = total;
+            }
+            u.finished = true;
+        });
+    }
+
+    fn after_run(&self, _plan: &MigrationPlan<V>, _result: &Result<()>, _duration: Duration) {
+        self.inner.state.lock().unwrap().current = None;
+    }
+}
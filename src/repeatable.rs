@@ -0,0 +1,45 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+use async_trait::async_trait;
+use okerr::Result;
+
+/// A repeatable migration is re-applied whenever its definition changes
+/// (e.g. views, triggers, reference-data loads), instead of getting a new version each time.
+/// It is identified by its name and its checksum, not by a version.
+///
+/// `Migratex` runs the repeatable migrations after the versioned ones
+/// (when migrating up to the latest version), whenever their checksum
+/// differs from the one stored in the metadata (see `Metadata::checksums`).
+#[async_trait]
+pub trait RepeatableMigration<MigContext>: Send + Sync {
+    /// The unique name of the migration.
+    fn name(&self) -> &str;
+
+    /// The checksum of the definition of the migration (see [`checksum`]).
+    /// The migration is re-run when it changes.
+    fn checksum(&self) -> String;
+
+    /// Apply (or re-apply) the migration. It must be idempotent.
+    async fn run(&self, ctx: &mut MigContext) -> Result<()>;
+}
+
+/// BoxRepeatable is the type of a repeatable migration.
+pub type BoxRepeatable<MigContext> = Box<dyn RepeatableMigration<MigContext> + Send + Sync>;
+
+/// Compute a checksum of some content (e.g. the SQL definition of a view),
+/// stable across builds and platforms (FNV-1a 64 bits, as hex).
+pub fn checksum(content: impl AsRef<[u8]>) -> String {
+    let hash = content
+        .as_ref()
+        .iter()
+        .fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+        });
+
+    format!("{hash:016x}")
+}
@@ -0,0 +1,128 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use okerr::Error;
+
+/// The delay between two attempts of a step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backoff {
+    /// Always the same delay.
+    Fixed(Duration),
+    /// A delay starting at `initial`, doubled after each attempt, up to `max`.
+    Exponential { initial: Duration, max: Duration },
+}
+
+impl Backoff {
+    /// Get the delay before the next attempt,
+    /// `attempt` being the number of the failed attempt (1 for the first one).
+    pub fn delay(&self, attempt: u32) -> Duration {
+        match *self {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, max } => {
+                let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+                initial.saturating_mul(factor).min(max)
+            }
+        }
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff::Exponential {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(5),
+        }
+    }
+}
+
+/// A predicate deciding whether an error is transient (worth retrying).
+type TransientFn = Arc<dyn Fn(&Error) -> bool + Send + Sync>;
+
+/// RetryPolicy retries the steps failing with transient errors
+/// (e.g. `SQLITE_BUSY`, temporary I/O error).
+///
+/// Only the migrations opting in (`Migration::retryable`, for idempotent steps) are retried.
+/// By default, every error is considered transient (see `with_transient`).
+///
+/// Example:
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use migratex::{Backoff, RetryPolicy};
+///
+/// let policy = RetryPolicy::new(5)
+///     .with_backoff(Backoff::Fixed(Duration::from_millis(200)))
+///     .with_transient(|e| e.to_string().contains("database is locked"));
+/// ```
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Backoff,
+    transient: TransientFn,
+}
+
+impl RetryPolicy {
+    /// Create a new RetryPolicy, with the maximum number of attempts of a step
+    /// (including the first one, at least 1).
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            backoff: Backoff::default(),
+            transient: Arc::new(|_| true),
+        }
+    }
+
+    /// Set the delay between two attempts.
+    /// Default: exponential, from 100ms up to 5s.
+    pub fn with_backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set the predicate deciding whether an error is transient (worth retrying).
+    pub fn with_transient(
+        mut self,
+        transient: impl Fn(&Error) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.transient = Arc::new(transient);
+        self
+    }
+
+    /// Get the maximum number of attempts of a step.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Get the delay between two attempts.
+    pub fn backoff(&self) -> Backoff {
+        self.backoff
+    }
+
+    /// Whether an error is transient (worth retrying).
+    pub fn is_transient(&self, error: &Error) -> bool {
+        (self.transient)(error)
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl std::fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff", &self.backoff)
+            .finish_non_exhaustive()
+    }
+}
@@ -5,16 +5,24 @@
 // information and documentation: https://github.com/nicolab/migratex
 // -----------------------------------------------------------------------------
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs;
 use std::path::Path;
 
 use okerr::Result;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::Value;
 
-use crate::{MetaStatus, Metadata, init_meta_datetimes_if_empty, meta_loaded};
+use crate::trace::trace_event;
+use crate::{MetaStatus, Metadata, Version, init_meta_datetimes_if_empty, meta_loaded};
 
 /// JsonMetadata provides JSON file-based storage for migration metadata.
 /// Metadata is stored in a JSON file on the file system.
+/// The version type `V` is `i32` by default (see [`Version`]).
+///
+/// A file can hold several tracks (e.g. `"db"`, `"files"`), each with its own version line
+/// (see `load_or_init_track`): the default track is the root object,
+/// the named tracks are stored under its `tracks` key.
 ///
 /// # Example
 ///
@@ -23,43 +31,68 @@ use crate::{MetaStatus, Metadata, init_meta_datetimes_if_empty, meta_loaded};
 /// use okerr::Result;
 ///
 /// fn main() -> Result<()> {
+///     let path = "metadata.json";
+/// #   let path = std::env::temp_dir().join("migratex-doc-metadata.json");
+///
 ///     // Load or initialize metadata
-///     let mut meta = JsonMetadata::load_or_init("metadata.json")?;
+///     let mut meta = JsonMetadata::load_or_init(&path)?;
 ///
 ///     // Modify metadata
 ///     meta.set_version(1);
 ///
 ///     // Save explicitly
-///     meta.save("metadata.json")?;
+///     meta.save(&path)?;
 ///
 ///     Ok(())
 /// }
 /// ```
 #[cfg(feature = "json")]
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct JsonMetadata {
-    pub version: i32,
+#[serde(bound(deserialize = "V: Ord + Deserialize<'de>"))]
+pub struct JsonMetadata<V = i32> {
+    pub version: V,
     pub app_version: String,
+    /// Whether `app_version` was set by the application
+    /// (absent from the files of the previous releases).
+    #[serde(default)]
+    pub app_version_recorded: bool,
     pub status: MetaStatus,
     pub created_at: String,
     pub updated_at: String,
+    /// The versions of the applied migrations.
+    #[serde(default)]
+    pub applied: BTreeSet<V>,
+    /// The versions of the skipped migrations (filtered out by their tags).
+    #[serde(default)]
+    pub skipped: BTreeSet<V>,
+    /// The checksums of the applied repeatable migrations, by name.
+    #[serde(default)]
+    pub checksums: BTreeMap<String, String>,
+    /// The name of the track (`None` for the default track).
+    #[serde(skip)]
+    track: Option<String>,
 }
 
 #[cfg(feature = "json")]
-impl Default for JsonMetadata {
+impl<V: Version> Default for JsonMetadata<V> {
     fn default() -> Self {
         Self {
-            version: 0,
+            version: V::zero(),
             app_version: String::new(),
+            app_version_recorded: false,
             status: MetaStatus::Clean,
             created_at: String::new(),
             updated_at: String::new(),
+            applied: BTreeSet::new(),
+            skipped: BTreeSet::new(),
+            checksums: BTreeMap::new(),
+            track: None,
         }
     }
 }
 
 #[cfg(feature = "json")]
-impl JsonMetadata {
+impl<V: Version + Serialize + DeserializeOwned> JsonMetadata<V> {
     /// Load metadata from a JSON file, or initialize a new one if it doesn't exist.
     pub fn load_or_init(path: impl AsRef<Path>) -> Result<Self> {
         let path = path.as_ref();
@@ -68,30 +101,109 @@ impl JsonMetadata {
             let meta: Self = serde_json::from_str(&txt)?;
             meta_loaded(meta)
         } else {
-            Self::init_new(path)
+            Self::init_new(path, None)
         }
     }
 
-    /// Save metadata to a JSON file.
+    /// Load the metadata of a named track from a JSON file,
+    /// or initialize it if the file or the track doesn't exist.
+    /// The other tracks of the file are left untouched.
+    pub fn load_or_init_track(path: impl AsRef<Path>, track: impl Into<String>) -> Result<Self> {
+        let path = path.as_ref();
+        let track = track.into();
+
+        let stored = read_json(path)?
+            .and_then(|mut root| root.get_mut(TRACKS)?.get_mut(&track).map(Value::take));
+
+        match stored {
+            Some(value) => {
+                let mut meta: Self = serde_json::from_value(value)?;
+                meta.track = Some(track);
+                meta_loaded(meta)
+            }
+            None => Self::init_new(path, Some(track)),
+        }
+    }
+
+    /// Get the name of the track (`None` for the default track).
+    pub fn track(&self) -> Option<&str> {
+        self.track.as_deref()
+    }
+
+    /// Save metadata to a JSON file (in its track, the other tracks are preserved).
     pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
-        let txt = serde_json::to_string_pretty(self)?;
+        let path = path.as_ref();
+        let stored = read_json(path)?;
+
+        let root = match &self.track {
+            None => {
+                let mut root = serde_json::to_value(self)?;
+                if let Some(tracks) = stored.and_then(|mut s| s.get_mut(TRACKS).map(Value::take)) {
+                    root[TRACKS] = tracks;
+                }
+                root
+            }
+            Some(track) => {
+                let mut root = match stored {
+                    Some(root) => root,
+                    None => serde_json::to_value(Self::default())?,
+                };
+                if !root[TRACKS].is_object() {
+                    root[TRACKS] = Value::Object(Default::default());
+                }
+                root[TRACKS][track] = serde_json::to_value(self)?;
+                root
+            }
+        };
+
+        let txt = serde_json::to_string_pretty(&root)?;
         fs::write(path, txt)?;
+
+        trace_event!(
+            debug,
+            path = %path.display(),
+            track = self.track().unwrap_or_default(),
+            version = %self.version,
+            status = self.to_status_str(),
+            "migratex metadata saved"
+        );
+
         Ok(())
     }
 
     /// Initialize a new metadata instance and save it.
-    fn init_new(path: impl AsRef<Path>) -> Result<Self> {
-        let mut meta = Self::default();
-        meta.set_version(0);
+    fn init_new(path: impl AsRef<Path>, track: Option<String>) -> Result<Self> {
+        let mut meta = Self {
+            track,
+            ..Self::default()
+        };
+        meta.set_version(V::zero());
         meta.set_status(MetaStatus::Clean);
-        meta.set_app_version(env!("CARGO_PKG_VERSION").to_string());
         init_meta_datetimes_if_empty(&mut meta);
         meta.save(path)?;
         Ok(meta)
     }
 }
 
+/// The key of the named tracks, in the root object.
+const TRACKS: &str = "tracks";
+
+/// Read a JSON file, if it exists.
+#[cfg(feature = "json")]
+fn read_json(path: &Path) -> Result<Option<Value>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let txt = fs::read_to_string(path)?;
+    Ok(Some(serde_json::from_str(&txt)?))
+}
+
 #[cfg(feature = "json")]
-impl Metadata for JsonMetadata {
-    crate::metadata_accessors!();
+impl<V: Version> Metadata<V> for JsonMetadata<V> {
+    crate::metadata_accessors!(V);
+    crate::applied_versions_accessors!(V);
+    crate::skipped_versions_accessors!(V);
+    crate::checksums_accessors!();
+    crate::app_version_recorded_accessors!();
 }
@@ -8,11 +8,23 @@
 #[cfg(feature = "json")]
 mod json_metadata;
 
+#[cfg(feature = "sqlx")]
+mod sqlite_backup;
+
 #[cfg(feature = "sqlx")]
 mod sqlite_metadata;
 
+#[cfg(feature = "sqlx")]
+mod sqlite_schema;
+
 #[cfg(feature = "json")]
 pub use json_metadata::*;
 
+#[cfg(feature = "sqlx")]
+pub use sqlite_backup::*;
+
 #[cfg(feature = "sqlx")]
 pub use sqlite_metadata::*;
+
+#[cfg(feature = "sqlx")]
+pub use sqlite_schema::*;
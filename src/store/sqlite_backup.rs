@@ -0,0 +1,355 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+use std::fs;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use okerr::{Context, Result, ensure};
+use sqlx::pool::PoolConnection;
+use sqlx::{Sqlite, SqliteConnection, SqlitePool};
+
+use crate::backup::{prune, snapshot_id};
+use crate::trace::trace_event;
+use crate::{Backup, Snapshot, SqliteStorage};
+
+/// The schema name of a snapshot attached to restore it.
+const ATTACHED: &str = "migratex_backup";
+
+/// SqliteBackup takes consistent online copies of a SQLite database (`VACUUM INTO`),
+/// one `<id>.db` file per snapshot, and restores them through the pool
+/// (the application can keep its connections open).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use migratex::{Backup, SqliteStorage, connect_to_sqlite};
+/// use std::sync::Arc;
+/// use std::path::PathBuf;
+/// use okerr::Result;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let pool = connect_to_sqlite(PathBuf::from("app.db")).await?;
+///     let storage = SqliteStorage::new(Arc::new(pool));
+///
+///     let backup = storage.backup("backups").with_keep(5);
+///     let snapshot = backup.snapshot().await?;
+///
+///     // Later, restore it
+///     backup.restore(&snapshot).await?;
+///
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "sqlx")]
+#[derive(Debug, Clone)]
+pub struct SqliteBackup {
+    pool: Arc<SqlitePool>,
+    dir: PathBuf,
+    keep: Option<usize>,
+}
+
+#[cfg(feature = "sqlx")]
+impl SqliteBackup {
+    /// Create a new SqliteBackup of the database of `pool`, storing the snapshots in `dir`.
+    pub fn new(pool: Arc<SqlitePool>, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            pool,
+            dir: dir.into(),
+            keep: None,
+        }
+    }
+
+    /// Keep only the `keep` most recent snapshots (at least 1).
+    /// Default: all the snapshots are kept.
+    pub fn with_keep(mut self, keep: usize) -> Self {
+        self.keep = Some(keep.max(1));
+        self
+    }
+
+    /// Get the directory of the snapshots.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Replace the schema and the rows of the main database
+    /// by the ones of the attached snapshot.
+    async fn copy_attached(conn: &mut SqliteConnection) -> Result<()> {
+        let current = schema_objects(conn, "main").await?;
+
+        // Views and triggers first, the indexes are dropped with their tables
+        // (and the shadow tables with their virtual table)
+        for kind in ["view", "trigger", "virtual", "table"] {
+            let keyword = if kind == "virtual" { "TABLE" } else { kind };
+            for (_, name, _) in current.iter().filter(|(t, _, _)| t == kind) {
+                sqlx::query(&format!(
+                    "DROP {} IF EXISTS main.{}",
+                    keyword.to_uppercase(),
+                    quote(name)
+                ))
+                .execute(&mut *conn)
+                .await?;
+            }
+        }
+
+        let schema = schema_objects(conn, ATTACHED).await?;
+        let is_table = |kind: &str| kind == "table" || kind == "virtual";
+
+        // Tables (the shadow tables are created by their virtual table),
+        // then their rows, then the indexes, views and triggers using them
+        for (_, _, sql) in schema.iter().filter(|(t, _, _)| is_table(t)) {
+            sqlx::query(sql).execute(&mut *conn).await?;
+        }
+
+        // The rows of a virtual table are stored in its shadow tables (if any)
+        for (_, name, _) in schema
+            .iter()
+            .filter(|(t, _, _)| t == "table" || t == "shadow")
+        {
+            copy_rows(conn, name).await?;
+        }
+
+        for (_, _, sql) in schema
+            .iter()
+            .filter(|(t, _, _)| !is_table(t) && t != "shadow")
+        {
+            sqlx::query(sql).execute(&mut *conn).await?;
+        }
+
+        // The AUTOINCREMENT counters
+        let sequence: Option<(String,)> = sqlx::query_as(&format!(
+            "SELECT name FROM {ATTACHED}.sqlite_master WHERE name = 'sqlite_sequence'"
+        ))
+        .fetch_optional(&mut *conn)
+        .await?;
+
+        if sequence.is_some() {
+            sqlx::query("DELETE FROM main.sqlite_sequence")
+                .execute(&mut *conn)
+                .await?;
+            sqlx::query(&format!(
+                "INSERT INTO main.sqlite_sequence SELECT * FROM {ATTACHED}.sqlite_sequence"
+            ))
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sqlx")]
+#[async_trait]
+impl Backup for SqliteBackup {
+    async fn snapshot(&self) -> Result<Snapshot> {
+        fs::create_dir_all(&self.dir)?;
+
+        let id = snapshot_id(&self.dir, ".db");
+        let path = self.dir.join(format!("{id}.db"));
+
+        sqlx::query("VACUUM INTO ?")
+            .bind(path.to_string_lossy())
+            .execute(&*self.pool)
+            .await
+            .with_context(|| format!("failed to back up the database to {}", path.display()))?;
+
+        trace_event!(info, id = %id, path = %path.display(), "migratex backup snapshot taken");
+
+        prune(self.snapshots().await?, self.keep)?;
+
+        Ok(Snapshot { id, path })
+    }
+
+    async fn restore(&self, snapshot: &Snapshot) -> Result<()> {
+        ensure!(
+            snapshot.path.is_file(),
+            "backup snapshot {} not found",
+            snapshot.path.display()
+        );
+
+        let mut conn = RestoreConnection {
+            conn: self.pool.acquire().await?,
+            dirty: false,
+        };
+
+        let (foreign_keys,): (i64,) = sqlx::query_as("PRAGMA foreign_keys")
+            .fetch_one(&mut *conn)
+            .await?;
+
+        // Until put back as it was
+        conn.dirty = true;
+
+        sqlx::query(&format!("ATTACH DATABASE ? AS {ATTACHED}"))
+            .bind(snapshot.path.to_string_lossy())
+            .execute(&mut *conn)
+            .await?;
+
+        // Cannot be changed inside a transaction
+        sqlx::query("PRAGMA foreign_keys = OFF")
+            .execute(&mut *conn)
+            .await?;
+
+        let restored = async {
+            sqlx::query("BEGIN IMMEDIATE").execute(&mut *conn).await?;
+
+            match Self::copy_attached(&mut conn).await {
+                Ok(()) => {
+                    sqlx::query("COMMIT").execute(&mut *conn).await?;
+                    Ok(())
+                }
+                Err(e) => {
+                    sqlx::query("ROLLBACK").execute(&mut *conn).await?;
+                    Err(e)
+                }
+            }
+        }
+        .await;
+
+        // Put the connection back as it was, even on failure
+        sqlx::query(&format!("PRAGMA foreign_keys = {foreign_keys}"))
+            .execute(&mut *conn)
+            .await?;
+        sqlx::query(&format!("DETACH DATABASE {ATTACHED}"))
+            .execute(&mut *conn)
+            .await?;
+        conn.dirty = false;
+
+        restored.with_context(|| {
+            format!(
+                "failed to restore the database from {}",
+                snapshot.path.display()
+            )
+        })?;
+
+        trace_event!(info, id = %snapshot.id, "migratex backup snapshot restored");
+
+        Ok(())
+    }
+
+    async fn snapshots(&self) -> Result<Vec<Snapshot>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut snapshots = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|e| e == "db") {
+                snapshots.push(Snapshot {
+                    id: path
+                        .file_stem()
+                        .map(|s| s.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    path,
+                });
+            }
+        }
+
+        snapshots.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(snapshots)
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl SqliteStorage {
+    /// Create a SqliteBackup of the database of this storage, storing the snapshots in `dir`.
+    pub fn backup(&self, dir: impl Into<PathBuf>) -> SqliteBackup {
+        SqliteBackup::new(self.pool.clone(), dir)
+    }
+}
+
+/// A pooled connection used by a restore, closed instead of being put back in the pool
+/// when the restore did not end with the connection as it was
+/// (snapshot attached, foreign keys off or transaction open, e.g. on error or when dropped).
+#[cfg(feature = "sqlx")]
+struct RestoreConnection {
+    conn: PoolConnection<Sqlite>,
+    dirty: bool,
+}
+
+#[cfg(feature = "sqlx")]
+impl Deref for RestoreConnection {
+    type Target = SqliteConnection;
+
+    fn deref(&self) -> &SqliteConnection {
+        &self.conn
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl DerefMut for RestoreConnection {
+    fn deref_mut(&mut self) -> &mut SqliteConnection {
+        &mut self.conn
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl Drop for RestoreConnection {
+    fn drop(&mut self) {
+        if self.dirty {
+            self.conn.close_on_drop();
+        }
+    }
+}
+
+/// Get the objects of a schema (`main` or attached) as `(kind, name, sql)`,
+/// except the internal ones (`sqlite_*`).
+/// The kind of a table is `table`, `virtual` or `shadow` (a table storing a virtual table).
+async fn schema_objects(
+    conn: &mut SqliteConnection,
+    schema: &str,
+) -> Result<Vec<(String, String, String)>> {
+    let objects = sqlx::query_as(&format!(
+        "SELECT coalesce(t.type, m.type), m.name, m.sql
+         FROM {schema}.sqlite_master AS m
+         LEFT JOIN pragma_table_list AS t
+             ON m.type = 'table' AND t.schema = ? AND t.name = m.name
+         WHERE substr(m.name, 1, 7) <> 'sqlite_' AND m.sql IS NOT NULL"
+    ))
+    .bind(schema)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(objects)
+}
+
+/// Copy the rows of a table from the attached snapshot, replacing the rows of `main`
+/// (a new shadow table has the initial rows of its virtual table).
+/// Only the insertable columns are copied, the generated columns are computed again.
+async fn copy_rows(conn: &mut SqliteConnection, table: &str) -> Result<()> {
+    let columns: Vec<(String,)> =
+        sqlx::query_as("SELECT name FROM pragma_table_xinfo(?, ?) WHERE hidden = 0 ORDER BY cid")
+            .bind(table)
+            .bind(ATTACHED)
+            .fetch_all(&mut *conn)
+            .await?;
+    let columns = columns
+        .iter()
+        .map(|(c,)| quote(c))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    sqlx::query(&format!("DELETE FROM main.{}", quote(table)))
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(&format!(
+        "INSERT INTO main.{0} ({columns}) SELECT {columns} FROM {ATTACHED}.{0}",
+        quote(table)
+    ))
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Quote an SQL identifier.
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
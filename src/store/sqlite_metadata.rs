@@ -5,15 +5,21 @@
 // information and documentation: https://github.com/nicolab/migratex
 // -----------------------------------------------------------------------------
 
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+    sync::Arc,
+};
 
 use okerr::{Context, Result, ensure};
 use sqlx::{
-    Row, SqlitePool,
-    sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
+    Row, Sqlite, SqlitePool, Transaction,
+    query::{Query, QueryAs},
+    sqlite::{SqliteArguments, SqliteConnectOptions, SqliteJournalMode, SqlitePoolOptions},
 };
 
-use crate::{MetaStatus, Metadata, init_meta_datetimes_if_empty, meta_loaded};
+use crate::trace::trace_event;
+use crate::{MetaStatus, Metadata, Version, init_meta_datetimes_if_empty, meta_loaded};
 
 /// Connect to SQLite database.
 #[cfg(feature = "sqlx")]
@@ -67,6 +73,9 @@ pub async fn connect_to_sqlite(db_path: PathBuf) -> Result<SqlitePool> {
 pub struct SqliteStorage {
     pub pool: Arc<SqlitePool>,
     pub table_name: String,
+    /// The name of the track (`None` for the default track),
+    /// the named tracks are stored in the `<table_name>_tracks` table.
+    pub track: Option<String>,
 }
 
 #[cfg(feature = "sqlx")]
@@ -76,6 +85,7 @@ impl SqliteStorage {
         Self {
             pool,
             table_name: "_migratex_metadata".to_string(),
+            track: None,
         }
     }
 
@@ -84,10 +94,36 @@ impl SqliteStorage {
         self.table_name = name.into();
         self
     }
+
+    /// Set the track (e.g. `"db"`, `"files"`): each track has its own version line
+    /// in the same database.
+    pub fn with_track(mut self, track: impl Into<String>) -> Self {
+        self.track = Some(track.into());
+        self
+    }
+
+    /// Get the metadata table of the track, and the condition selecting its row.
+    fn track_table(&self) -> (String, &str) {
+        match self.track {
+            None => (self.table_name.clone(), "id = 1"),
+            Some(_) => (format!("{}_tracks", self.table_name), "track = ?"),
+        }
+    }
+
+    /// Get the table of a set of the track (`applied`, `skipped`, `checksums`),
+    /// and the condition selecting its rows.
+    fn versions_table(&self, set: &str) -> (String, &str) {
+        match self.track {
+            None => (format!("{}_{set}", self.table_name), "1 = 1"),
+            Some(_) => (format!("{}_tracks_{set}", self.table_name), "track = ?"),
+        }
+    }
 }
 
 /// SqliteMetadata provides SQLite-based storage for migration metadata.
 /// Metadata is stored in a table within the SQLite database.
+/// The version type `V` is `i32` by default (see [`Version`]),
+/// it is stored encoded by `Version::to_storage`.
 ///
 /// # Example
 ///
@@ -100,7 +136,9 @@ impl SqliteStorage {
 /// #[tokio::main]
 /// async fn main() -> Result<()> {
 ///     // Connect to SQLite database
-///     let pool = connect_to_sqlite(PathBuf::from("app.db")).await?;
+///     let path = PathBuf::from("app.db");
+/// #   let path = std::env::temp_dir().join("migratex-doc-app.db");
+///     let pool = connect_to_sqlite(path).await?;
 ///     let storage = SqliteStorage::new(Arc::new(pool));
 ///
 ///     // Load or initialize metadata
@@ -117,29 +155,45 @@ impl SqliteStorage {
 /// ```
 #[cfg(feature = "sqlx")]
 #[derive(Debug, Clone)]
-pub struct SqliteMetadata {
-    pub version: i32,
+pub struct SqliteMetadata<V = i32> {
+    pub version: V,
     pub app_version: String,
+    /// Whether `app_version` was set by the application
+    /// (`0` in the tables of the previous releases).
+    pub app_version_recorded: bool,
     pub status: MetaStatus,
     pub created_at: String,
     pub updated_at: String,
+    /// The versions of the applied migrations
+    /// (stored in the `<table_name>_applied` table).
+    pub applied: BTreeSet<V>,
+    /// The versions of the skipped migrations (filtered out by their tags)
+    /// (stored in the `<table_name>_skipped` table).
+    pub skipped: BTreeSet<V>,
+    /// The checksums of the applied repeatable migrations, by name
+    /// (stored in the `<table_name>_checksums` table).
+    pub checksums: BTreeMap<String, String>,
 }
 
 #[cfg(feature = "sqlx")]
-impl Default for SqliteMetadata {
+impl<V: Version> Default for SqliteMetadata<V> {
     fn default() -> Self {
         Self {
-            version: 0,
+            version: V::zero(),
             app_version: String::new(),
+            app_version_recorded: false,
             status: MetaStatus::Clean,
             created_at: String::new(),
             updated_at: String::new(),
+            applied: BTreeSet::new(),
+            skipped: BTreeSet::new(),
+            checksums: BTreeMap::new(),
         }
     }
 }
 
 #[cfg(feature = "sqlx")]
-impl SqliteMetadata {
+impl<V: Version> SqliteMetadata<V> {
     /// Load metadata from the database, or initialize a new one if it doesn't exist.
     pub async fn load_or_init(storage: &SqliteStorage) -> Result<Self> {
         Self::ensure_table(storage).await?;
@@ -155,53 +209,294 @@ impl SqliteMetadata {
     pub async fn save(&self, storage: &SqliteStorage) -> Result<()> {
         Self::ensure_table(storage).await?;
 
+        let mut tx = storage.pool.begin().await?;
+        let (table, _) = storage.track_table();
+
+        let (key, key_value) = match &storage.track {
+            None => ("id", "1"),
+            Some(_) => ("track", "?"),
+        };
+
         sqlx::query(&format!(
-            "INSERT INTO {} (id, version, status, app_version, created_at, updated_at)
-             VALUES (1, ?, ?, ?, ?, ?)
-             ON CONFLICT(id) DO UPDATE SET
+            "INSERT INTO {table}
+                ({key}, version, status, app_version, app_version_recorded, created_at, updated_at)
+             VALUES ({key_value}, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT({key}) DO UPDATE SET
                 version = excluded.version,
                 status = excluded.status,
                 app_version = excluded.app_version,
-                updated_at = excluded.updated_at",
-            storage.table_name
+                app_version_recorded = excluded.app_version_recorded,
+                updated_at = excluded.updated_at"
         ))
-        .bind(self.version)
+        .bind_track(storage)
+        .bind(self.version.to_storage())
         .bind(self.to_status_str())
         .bind(&self.app_version)
+        .bind(self.app_version_recorded)
         .bind(&self.created_at)
         .bind(&self.updated_at)
-        .execute(&*storage.pool)
+        .execute(&mut *tx)
         .await?;
 
+        Self::save_versions(&mut tx, storage, "applied", &self.applied).await?;
+        Self::save_versions(&mut tx, storage, "skipped", &self.skipped).await?;
+        Self::save_checksums(&mut tx, storage, &self.checksums).await?;
+
+        tx.commit().await?;
+
+        trace_event!(
+            debug,
+            table = %storage.table_name,
+            track = storage.track.as_deref().unwrap_or_default(),
+            version = %self.version,
+            status = self.to_status_str(),
+            "migratex metadata saved"
+        );
+
         Ok(())
     }
 
+    /// Save a versions set (`applied`, `skipped`) of the track, replacing the stored one.
+    async fn save_versions(
+        tx: &mut Transaction<'_, Sqlite>,
+        storage: &SqliteStorage,
+        set: &str,
+        versions: &BTreeSet<V>,
+    ) -> Result<()> {
+        let (table, table_where) = storage.versions_table(set);
+
+        sqlx::query(&format!("DELETE FROM {table} WHERE {table_where}"))
+            .bind_track(storage)
+            .execute(&mut **tx)
+            .await?;
+
+        let (key, key_value) = match &storage.track {
+            None => ("", ""),
+            Some(_) => ("track, ", "?, "),
+        };
+
+        for v in versions {
+            sqlx::query(&format!(
+                "INSERT INTO {table} ({key}version) VALUES ({key_value}?)"
+            ))
+            .bind_track(storage)
+            .bind(v.to_storage())
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Load a versions set (`applied`, `skipped`) of the track.
+    async fn load_versions(storage: &SqliteStorage, set: &str) -> Result<BTreeSet<V>> {
+        let (table, table_where) = storage.versions_table(set);
+
+        let rows: Vec<(String,)> = sqlx::query_as(&format!(
+            "SELECT CAST(version AS TEXT) FROM {table} WHERE {table_where}"
+        ))
+        .bind_track(storage)
+        .fetch_all(&*storage.pool)
+        .await?;
+
+        rows.iter().map(|(v,)| V::from_storage(v)).collect()
+    }
+
+    /// Save the checksums of the repeatable migrations of the track, replacing the stored ones.
+    async fn save_checksums(
+        tx: &mut Transaction<'_, Sqlite>,
+        storage: &SqliteStorage,
+        checksums: &BTreeMap<String, String>,
+    ) -> Result<()> {
+        let (table, table_where) = storage.versions_table("checksums");
+
+        sqlx::query(&format!("DELETE FROM {table} WHERE {table_where}"))
+            .bind_track(storage)
+            .execute(&mut **tx)
+            .await?;
+
+        let (key, key_value) = match &storage.track {
+            None => ("", ""),
+            Some(_) => ("track, ", "?, "),
+        };
+
+        for (name, checksum) in checksums {
+            sqlx::query(&format!(
+                "INSERT INTO {table} ({key}name, checksum) VALUES ({key_value}?, ?)"
+            ))
+            .bind_track(storage)
+            .bind(name)
+            .bind(checksum)
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Load the checksums of the repeatable migrations of the track.
+    async fn load_checksums(storage: &SqliteStorage) -> Result<BTreeMap<String, String>> {
+        let (table, table_where) = storage.versions_table("checksums");
+
+        let rows: Vec<(String, String)> = sqlx::query_as(&format!(
+            "SELECT name, checksum FROM {table} WHERE {table_where}"
+        ))
+        .bind_track(storage)
+        .fetch_all(&*storage.pool)
+        .await?;
+
+        Ok(rows.into_iter().collect())
+    }
+
     /// Ensure the metadata table exists.
     async fn ensure_table(storage: &SqliteStorage) -> Result<()> {
+        sqlx::query(&main_table_sql(&storage.table_name))
+            .execute(&*storage.pool)
+            .await?;
+
+        Self::ensure_app_version_recorded(storage, &storage.table_name).await?;
+        Self::ensure_text_version(storage).await?;
+
+        for set in VERSIONS_SETS {
+            sqlx::query(&format!(
+                "CREATE TABLE IF NOT EXISTS {}_{set} (
+                    version TEXT PRIMARY KEY
+                )",
+                storage.table_name
+            ))
+            .execute(&*storage.pool)
+            .await?;
+        }
+
         sqlx::query(&format!(
-            "CREATE TABLE IF NOT EXISTS {} (
-                id INTEGER PRIMARY KEY CHECK (id = 1),
-                version INTEGER NOT NULL DEFAULT 0,
-                status TEXT NOT NULL DEFAULT 'Clean',
-                app_version TEXT NOT NULL,
-                created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
+            "CREATE TABLE IF NOT EXISTS {}_checksums (
+                name TEXT PRIMARY KEY,
+                checksum TEXT NOT NULL
             )",
             storage.table_name
         ))
         .execute(&*storage.pool)
         .await?;
 
+        if storage.track.is_some() {
+            sqlx::query(&format!(
+                "CREATE TABLE IF NOT EXISTS {}_tracks (
+                    track TEXT PRIMARY KEY,
+                    version TEXT NOT NULL DEFAULT '0',
+                    status TEXT NOT NULL DEFAULT 'Clean',
+                    app_version TEXT NOT NULL,
+                    app_version_recorded INTEGER NOT NULL DEFAULT 0,
+                    created_at TEXT NOT NULL,
+                    updated_at TEXT NOT NULL
+                )",
+                storage.table_name
+            ))
+            .execute(&*storage.pool)
+            .await?;
+
+            Self::ensure_app_version_recorded(storage, &format!("{}_tracks", storage.table_name))
+                .await?;
+
+            for set in VERSIONS_SETS {
+                sqlx::query(&format!(
+                    "CREATE TABLE IF NOT EXISTS {}_tracks_{set} (
+                        track TEXT NOT NULL,
+                        version TEXT NOT NULL,
+                        PRIMARY KEY (track, version)
+                    )",
+                    storage.table_name
+                ))
+                .execute(&*storage.pool)
+                .await?;
+            }
+
+            sqlx::query(&format!(
+                "CREATE TABLE IF NOT EXISTS {}_tracks_checksums (
+                    track TEXT NOT NULL,
+                    name TEXT NOT NULL,
+                    checksum TEXT NOT NULL,
+                    PRIMARY KEY (track, name)
+                )",
+                storage.table_name
+            ))
+            .execute(&*storage.pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Add the `app_version_recorded` column to a metadata table created by a previous release.
+    async fn ensure_app_version_recorded(storage: &SqliteStorage, table: &str) -> Result<()> {
+        let (exists,): (bool,) = sqlx::query_as(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info(?) WHERE name = 'app_version_recorded'",
+        )
+        .bind(table)
+        .fetch_one(&*storage.pool)
+        .await?;
+
+        if !exists {
+            sqlx::query(&format!(
+                "ALTER TABLE {table} ADD COLUMN app_version_recorded INTEGER NOT NULL DEFAULT 0"
+            ))
+            .execute(&*storage.pool)
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Convert the `version` column of a metadata table created by a previous release
+    /// from INTEGER to TEXT, to store any version type (e.g. `"0001.0010"` is kept as is).
+    async fn ensure_text_version(storage: &SqliteStorage) -> Result<()> {
+        let table = &storage.table_name;
+        let (kind,): (String,) =
+            sqlx::query_as("SELECT type FROM pragma_table_info(?) WHERE name = 'version'")
+                .bind(table)
+                .fetch_one(&*storage.pool)
+                .await?;
+
+        if kind.eq_ignore_ascii_case("TEXT") {
+            return Ok(());
+        }
+
+        // The type of a column cannot be changed, the table is rebuilt
+        let mut tx = storage.pool.begin().await?;
+
+        sqlx::query(&format!("ALTER TABLE {table} RENAME TO {table}_integer"))
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&main_table_sql(table))
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&format!(
+            "INSERT INTO {table}
+                 (id, version, status, app_version, app_version_recorded, created_at, updated_at)
+             SELECT id, CAST(version AS TEXT), status, app_version, app_version_recorded,
+                 created_at, updated_at
+             FROM {table}_integer"
+        ))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(&format!("DROP TABLE {table}_integer"))
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
         Ok(())
     }
 
     /// Load metadata from the database table.
     async fn load_from_db(storage: &SqliteStorage) -> Result<Option<Self>> {
+        let (table, table_where) = storage.track_table();
+
         let row = sqlx::query(&format!(
-            "SELECT version, status, app_version, created_at, updated_at
-             FROM {} WHERE id = 1",
-            storage.table_name
+            "SELECT CAST(version AS TEXT) AS version, status, app_version, app_version_recorded,
+                created_at, updated_at
+             FROM {table} WHERE {table_where}"
         ))
+        .bind_track(storage)
         .fetch_optional(&*storage.pool)
         .await?;
 
@@ -213,12 +508,18 @@ impl SqliteMetadata {
                 _ => MetaStatus::Clean,
             };
 
+            let version: String = row.try_get("version")?;
+
             Ok(Some(Self {
-                version: row.try_get("version")?,
+                version: V::from_storage(&version)?,
                 app_version: row.try_get("app_version")?,
+                app_version_recorded: row.try_get("app_version_recorded")?,
                 status,
                 created_at: row.try_get("created_at")?,
                 updated_at: row.try_get("updated_at")?,
+                applied: Self::load_versions(storage, "applied").await?,
+                skipped: Self::load_versions(storage, "skipped").await?,
+                checksums: Self::load_checksums(storage).await?,
             }))
         } else {
             Ok(None)
@@ -228,16 +529,60 @@ impl SqliteMetadata {
     /// Initialize a new metadata instance and save it.
     async fn init_new(storage: &SqliteStorage) -> Result<Self> {
         let mut meta = Self::default();
-        meta.set_version(0);
+        meta.set_version(V::zero());
         meta.set_status(MetaStatus::Clean);
-        meta.set_app_version(env!("CARGO_PKG_VERSION").to_string());
         init_meta_datetimes_if_empty(&mut meta);
         meta.save(storage).await?;
         Ok(meta)
     }
 }
 
+/// The statement creating the main metadata table (if missing).
+fn main_table_sql(table: &str) -> String {
+    format!(
+        "CREATE TABLE IF NOT EXISTS {table} (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            version TEXT NOT NULL DEFAULT '0',
+            status TEXT NOT NULL DEFAULT 'Clean',
+            app_version TEXT NOT NULL,
+            app_version_recorded INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )"
+    )
+}
+
+/// The sets of versions stored in their own table (`<table_name>_<set>`).
+const VERSIONS_SETS: [&str; 2] = ["applied", "skipped"];
+
+/// Bind the track of the storage (if any) to a query.
+trait BindTrack {
+    fn bind_track(self, storage: &SqliteStorage) -> Self;
+}
+
+impl<'q> BindTrack for Query<'q, Sqlite, SqliteArguments<'q>> {
+    fn bind_track(self, storage: &SqliteStorage) -> Self {
+        match &storage.track {
+            Some(track) => self.bind(track.clone()),
+            None => self,
+        }
+    }
+}
+
+impl<'q, O> BindTrack for QueryAs<'q, Sqlite, O, SqliteArguments<'q>> {
+    fn bind_track(self, storage: &SqliteStorage) -> Self {
+        match &storage.track {
+            Some(track) => self.bind(track.clone()),
+            None => self,
+        }
+    }
+}
+
 #[cfg(feature = "sqlx")]
-impl Metadata for SqliteMetadata {
-    crate::metadata_accessors!();
+impl<V: Version> Metadata<V> for SqliteMetadata<V> {
+    crate::metadata_accessors!(V);
+    crate::applied_versions_accessors!(V);
+    crate::skipped_versions_accessors!(V);
+    crate::checksums_accessors!();
+    crate::app_version_recorded_accessors!();
 }
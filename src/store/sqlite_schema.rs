@@ -0,0 +1,336 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+use std::fmt::{self, Display, Write};
+use std::fs;
+use std::path::Path;
+
+use okerr::{Context, Result, fail};
+use sqlx::SqlitePool;
+
+use crate::SqliteStorage;
+
+/// The environment variable to (re)write the schema snapshots instead of comparing them,
+/// e.g. `MIGRATEX_UPDATE_SNAPSHOTS=1 cargo test` (see `SqliteSchema::check_snapshot`).
+pub const UPDATE_SNAPSHOTS_ENV: &str = "MIGRATEX_UPDATE_SNAPSHOTS";
+
+/// SqliteSchema is a canonical text dump of the schema of a SQLite database:
+/// the database pragmas (`user_version`, `application_id`), then each table
+/// (columns, foreign keys, indexes, triggers) and view, ordered by name.
+///
+/// The tables are described from their pragmas rather than their `CREATE` statement,
+/// so a database migrated forward (e.g. with `ALTER TABLE ... ADD COLUMN`)
+/// has the same dump as a fresh one with the same schema.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use migratex::{SqliteSchema, SqliteStorage, connect_to_sqlite};
+/// use std::sync::Arc;
+/// use std::path::PathBuf;
+/// use okerr::Result;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     let pool = connect_to_sqlite(PathBuf::from("app.db")).await?;
+///     let storage = SqliteStorage::new(Arc::new(pool));
+///
+///     // After migrate_to_latest, compare with the committed snapshot
+///     let schema = storage.schema().await?;
+///     schema.check_snapshot("tests/snapshots/schema.txt")?;
+///
+///     Ok(())
+/// }
+/// ```
+#[cfg(feature = "sqlx")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqliteSchema {
+    text: String,
+}
+
+#[cfg(feature = "sqlx")]
+impl SqliteSchema {
+    /// Dump the schema of the database of `pool`.
+    pub async fn dump(pool: &SqlitePool) -> Result<Self> {
+        Self::dump_excluding(pool, None).await
+    }
+
+    /// Dump the schema, without the tables named `exclude` or `<exclude>_*`.
+    async fn dump_excluding(pool: &SqlitePool, exclude: Option<&str>) -> Result<Self> {
+        let mut text = String::new();
+
+        for pragma in ["application_id", "user_version"] {
+            let (value,): (i64,) = sqlx::query_as(&format!("PRAGMA {pragma}"))
+                .fetch_one(pool)
+                .await?;
+            writeln!(text, "pragma {pragma} = {value}")?;
+        }
+
+        let objects: Vec<(String, String, String, Option<String>)> = sqlx::query_as(
+            "SELECT type, name, tbl_name, sql FROM sqlite_master
+             WHERE substr(name, 1, 7) <> 'sqlite_' ORDER BY name",
+        )
+        .fetch_all(pool)
+        .await
+        .context("failed to read the SQLite schema")?;
+
+        let excluded = |table: &str| {
+            exclude.is_some_and(|e| table == e || table.starts_with(&format!("{e}_")))
+        };
+
+        for (kind, name, _, sql) in &objects {
+            if excluded(name) {
+                continue;
+            }
+
+            match kind.as_str() {
+                "table" => {
+                    writeln!(text, "\ntable {name}")?;
+                    Self::dump_table(pool, name, &mut text).await?;
+
+                    for (kind, trigger, table, sql) in &objects {
+                        if kind == "trigger" && table == name {
+                            writeln!(text, "  trigger {trigger}: {}", normalize(sql))?;
+                        }
+                    }
+                }
+                "view" => writeln!(text, "\nview {name}\n  {}", normalize(sql))?,
+                _ => {}
+            }
+        }
+
+        Ok(Self { text })
+    }
+
+    /// Dump the columns, foreign keys and indexes of a table.
+    async fn dump_table(pool: &SqlitePool, table: &str, text: &mut String) -> Result<()> {
+        let columns: Vec<(String, String, bool, Option<String>, i64)> = sqlx::query_as(
+            "SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?) ORDER BY cid",
+        )
+        .bind(table)
+        .fetch_all(pool)
+        .await?;
+
+        for (name, kind, not_null, default, pk) in columns {
+            write!(text, "  column {name} {kind}")?;
+            if not_null {
+                write!(text, " not null")?;
+            }
+            if let Some(default) = default {
+                write!(text, " default {default}")?;
+            }
+            if pk > 0 {
+                write!(text, " primary key {pk}")?;
+            }
+            writeln!(text)?;
+        }
+
+        let foreign_keys: Vec<(i64, String, String, Option<String>, String, String)> =
+            sqlx::query_as(
+                "SELECT id, \"table\", \"from\", \"to\", on_update, on_delete
+                 FROM pragma_foreign_key_list(?) ORDER BY id, seq",
+            )
+            .bind(table)
+            .fetch_all(pool)
+            .await?;
+
+        for (_, parent, from, to, on_update, on_delete) in foreign_keys {
+            writeln!(
+                text,
+                "  foreign key ({from}) references {parent} ({}) on update {on_update} on delete {on_delete}",
+                to.unwrap_or_default()
+            )?;
+        }
+
+        let indexes: Vec<(String, bool, String, bool)> = sqlx::query_as(
+            "SELECT name, \"unique\", origin, partial FROM pragma_index_list(?) ORDER BY name",
+        )
+        .bind(table)
+        .fetch_all(pool)
+        .await?;
+
+        for (name, unique, origin, partial) in indexes {
+            let columns: Vec<(Option<String>,)> =
+                sqlx::query_as("SELECT name FROM pragma_index_info(?) ORDER BY seqno")
+                    .bind(&name)
+                    .fetch_all(pool)
+                    .await?;
+
+            let columns: Vec<_> = columns
+                .into_iter()
+                .map(|(c,)| c.unwrap_or_else(|| "<expr>".to_string()))
+                .collect();
+
+            // The automatic indexes (unique / primary key constraints) are named by their origin
+            let name = if origin == "c" { name } else { origin };
+
+            write!(text, "  index {name} ({})", columns.join(", "))?;
+            if unique {
+                write!(text, " unique")?;
+            }
+            if partial {
+                write!(text, " partial")?;
+            }
+            writeln!(text)?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the canonical text of the schema.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Get the differences from `self` (expected) to `actual`, as removed (`-`)
+    /// and added (`+`) lines under their table / view.
+    /// `None` when the schemas are the same.
+    pub fn diff(&self, actual: &SqliteSchema) -> Option<String> {
+        if self == actual {
+            return None;
+        }
+
+        Some(diff_lines(&self.text, &actual.text))
+    }
+
+    /// Check that `actual` is the same schema (e.g. a database migrated forward
+    /// compared to a fresh one), failing with the differences.
+    pub fn check_same(&self, actual: &SqliteSchema) -> Result<()> {
+        match self.diff(actual) {
+            Some(diff) => fail!("the SQLite schemas differ:\n{diff}"),
+            None => Ok(()),
+        }
+    }
+
+    /// Save the schema to a snapshot file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::write(path, &self.text)
+            .with_context(|| format!("failed to write the schema snapshot {}", path.display()))
+    }
+
+    /// Load a schema from a snapshot file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read the schema snapshot {}", path.display()))?;
+
+        Ok(Self { text })
+    }
+
+    /// Compare the schema with a committed snapshot file, failing with the differences
+    /// (or when the snapshot is missing, so a snapshot not committed cannot pass unnoticed).
+    /// The snapshot is only written when the `MIGRATEX_UPDATE_SNAPSHOTS`
+    /// environment variable is set (to create it, or to accept a schema change).
+    pub fn check_snapshot(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+
+        if std::env::var_os(UPDATE_SNAPSHOTS_ENV).is_some() {
+            return self.save(path);
+        }
+
+        if !path.exists() {
+            fail!(
+                "the SQLite schema snapshot {} is missing (set {UPDATE_SNAPSHOTS_ENV}=1 to write it)",
+                path.display()
+            );
+        }
+
+        let expected = Self::load(path)?;
+
+        match expected.diff(self) {
+            Some(diff) => fail!(
+                "the SQLite schema differs from the snapshot {} (set {UPDATE_SNAPSHOTS_ENV}=1 to update it):\n{diff}",
+                path.display()
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl Display for SqliteSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl SqliteStorage {
+    /// Dump the schema of the database of this storage,
+    /// without the metadata tables (see `SqliteSchema`).
+    pub async fn schema(&self) -> Result<SqliteSchema> {
+        SqliteSchema::dump_excluding(&self.pool, Some(&self.table_name)).await
+    }
+}
+
+/// Normalize an SQL statement: whitespace runs collapsed to a single space.
+fn normalize(sql: &Option<String>) -> String {
+    sql.as_deref()
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Diff two texts by lines (longest common subsequence, blank lines ignored),
+/// keeping only the changed lines, each group under its section (the last unindented line).
+fn diff_lines(expected: &str, actual: &str) -> String {
+    let a: Vec<_> = expected.lines().filter(|l| !l.is_empty()).collect();
+    let b: Vec<_> = actual.lines().filter(|l| !l.is_empty()).collect();
+
+    // lcs[i][j]: the length of the longest common subsequence of a[i..] and b[j..]
+    let mut lcs = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lcs[i][j] = if a[i] == b[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = String::new();
+    let mut section = "";
+    let mut printed = None;
+    let (mut i, mut j) = (0, 0);
+
+    while i < a.len() || j < b.len() {
+        let (sign, line) = if i < a.len() && j < b.len() && a[i] == b[j] {
+            if !a[i].starts_with(' ') {
+                section = a[i];
+            }
+            i += 1;
+            j += 1;
+            continue;
+        } else if j < b.len() && (i == a.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            j += 1;
+            ('+', b[j - 1])
+        } else {
+            i += 1;
+            ('-', a[i - 1])
+        };
+
+        if !line.starts_with(' ') {
+            section = line;
+            printed = Some(section);
+        } else if !section.is_empty() && printed != Some(section) {
+            writeln!(out, "  {section}").ok();
+            printed = Some(section);
+        }
+
+        writeln!(out, "{sign} {line}").ok();
+    }
+
+    out
+}
@@ -0,0 +1,188 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+//! Helpers to test the migrations.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+
+use okerr::{Context, Result, ensure};
+
+use crate::migratex::Slot;
+use crate::{BoxMigration, MetaStatus, Metadata, Migratex, Version};
+
+/// MemoryMetadata is a metadata kept in memory (not stored),
+/// e.g. to run the migrations in tests.
+#[derive(Debug, Clone)]
+pub struct MemoryMetadata<V: Version = i32> {
+    pub version: V,
+    pub app_version: String,
+    pub app_version_recorded: bool,
+    pub status: MetaStatus,
+    pub created_at: String,
+    pub updated_at: String,
+    pub applied: BTreeSet<V>,
+    pub skipped: BTreeSet<V>,
+    pub checksums: BTreeMap<String, String>,
+}
+
+impl<V: Version> Default for MemoryMetadata<V> {
+    fn default() -> Self {
+        Self {
+            version: V::zero(),
+            app_version: String::new(),
+            app_version_recorded: false,
+            status: MetaStatus::Clean,
+            created_at: String::new(),
+            updated_at: String::new(),
+            applied: BTreeSet::new(),
+            skipped: BTreeSet::new(),
+            checksums: BTreeMap::new(),
+        }
+    }
+}
+
+impl<V: Version> Metadata<V> for MemoryMetadata<V> {
+    crate::metadata_accessors!(V);
+    crate::applied_versions_accessors!(V);
+    crate::skipped_versions_accessors!(V);
+    crate::checksums_accessors!();
+    crate::app_version_recorded_accessors!();
+}
+
+/// RoundTrip checks that the `down` of each migration truly reverts its `up`.
+///
+/// For each migration (in version order), a new context is migrated up to the previous version,
+/// then the migration is applied (`up`), rolled back (`down`), then applied again (`up`),
+/// comparing the snapshots of the state:
+/// the state after the `down` must be the state before the `up`,
+/// and the state after the second `up` must be the state after the first one.
+/// The irreversible migrations are only applied.
+///
+/// Example:
+///
+/// ```rust,no_run
+/// use migratex::testing::RoundTrip;
+/// use migratex::{BoxMigration, FnMigration};
+/// use okerr::Result;
+///
+/// #[derive(Default)]
+/// struct Context {
+///     tables: Vec<String>,
+/// }
+///
+/// fn migrations() -> Vec<BoxMigration<Context>> {
+///     vec![Box::new(FnMigration::new(
+///         1,
+///         "create_users",
+///         |ctx: &mut Context| {
+///             Box::pin(async move {
+///                 ctx.tables.push("users".to_string());
+///                 Ok(())
+///             })
+///         },
+///         |ctx: &mut Context| {
+///             Box::pin(async move {
+///                 ctx.tables.clear();
+///                 Ok(())
+///             })
+///         },
+///     ))]
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<()> {
+///     RoundTrip::new(migrations())
+///         .run(
+///             async || Ok(Context::default()),
+///             async |ctx: &Context| Ok(ctx.tables.clone()),
+///         )
+///         .await
+/// }
+/// ```
+pub struct RoundTrip<MigContext, V: Version = i32> {
+    migrations: Vec<BoxMigration<MigContext, V>>,
+}
+
+impl<MigContext, V: Version> RoundTrip<MigContext, V> {
+    /// Create a new RoundTrip for a migrations list.
+    pub fn new(migrations: Vec<BoxMigration<MigContext, V>>) -> Self {
+        Self { migrations }
+    }
+
+    /// Run the round trips, with a new context created by `new_context` for each migration,
+    /// and the state taken by `snapshot`.
+    /// Fails at the first migration whose snapshots do not match (or failing to run).
+    pub async fn run<S: PartialEq + Debug>(
+        self,
+        new_context: impl AsyncFn() -> Result<MigContext>,
+        snapshot: impl AsyncFn(&MigContext) -> Result<S>,
+    ) -> Result<()> {
+        let mut steps: Vec<_> = self
+            .migrations
+            .iter()
+            .map(|m| (m.version(), m.name().to_string(), m.reversible()))
+            .collect();
+        steps.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut mx = Migratex::owned(
+            new_context().await?,
+            MemoryMetadata::<V>::default(),
+            self.migrations,
+        );
+
+        let mut previous = V::zero();
+
+        for (index, (version, name, reversible)) in steps.into_iter().enumerate() {
+            let step = || format!("round trip of migration {version} ({name})");
+
+            // A new context (the first one is new), not left by the previous round trips
+            if index > 0 {
+                mx.ctx = Slot::Owned(new_context().await?);
+                mx.meta = Slot::Owned(MemoryMetadata::default());
+            }
+
+            mx.migrate_to(previous.clone())
+                .await
+                .with_context(|| format!("{}: up to the previous version", step()))?;
+            let before = snapshot(mx.context()).await?;
+
+            mx.migrate_to(version.clone())
+                .await
+                .with_context(|| format!("{}: up", step()))?;
+            let after = snapshot(mx.context()).await?;
+
+            if reversible {
+                mx.migrate_to(previous.clone())
+                    .await
+                    .with_context(|| format!("{}: down", step()))?;
+                let reverted = snapshot(mx.context()).await?;
+
+                ensure!(
+                    reverted == before,
+                    "{}: the down does not revert the up\n  before up:  {before:?}\n  after down: {reverted:?}",
+                    step()
+                );
+
+                mx.migrate_to(version.clone())
+                    .await
+                    .with_context(|| format!("{}: up again", step()))?;
+                let again = snapshot(mx.context()).await?;
+
+                ensure!(
+                    again == after,
+                    "{}: the up applied again does not give the same state\n  first up:  {after:?}\n  second up: {again:?}",
+                    step()
+                );
+            }
+
+            previous = version;
+        }
+
+        Ok(())
+    }
+}
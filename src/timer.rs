@@ -0,0 +1,115 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// Executor-agnostic timer (a thread per delay), to not depend on an async runtime.
+
+use std::future::{Future, poll_fn};
+use std::pin::{Pin, pin};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+/// State shared between a delay and its timer thread.
+#[derive(Default)]
+struct DelayState {
+    elapsed: bool,
+    dropped: bool,
+    waker: Option<Waker>,
+}
+
+/// A future completing after a duration.
+pub(crate) struct Delay {
+    state: Arc<Mutex<DelayState>>,
+    thread: Thread,
+}
+
+impl Delay {
+    /// Start a delay.
+    pub(crate) fn new(duration: Duration) -> Self {
+        let state = Arc::new(Mutex::new(DelayState::default()));
+        let shared = Arc::clone(&state);
+        // Never elapses when too far to be represented
+        let deadline = Instant::now().checked_add(duration);
+
+        let handle = thread::spawn(move || {
+            loop {
+                let now = Instant::now();
+                if shared.lock().unwrap().dropped {
+                    return;
+                }
+                // Unparked early when the delay is dropped
+                match deadline {
+                    Some(deadline) if now >= deadline => break,
+                    Some(deadline) => thread::park_timeout(deadline - now),
+                    None => thread::park(),
+                }
+            }
+
+            let mut state = shared.lock().unwrap();
+            state.elapsed = true;
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+
+        Self {
+            state,
+            thread: handle.thread().clone(),
+        }
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.elapsed {
+            Poll::Ready(())
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Delay {
+    fn drop(&mut self) {
+        self.state.lock().unwrap().dropped = true;
+        self.thread.unpark();
+    }
+}
+
+/// Wait for a duration.
+pub(crate) async fn sleep(duration: Duration) {
+    if !duration.is_zero() {
+        Delay::new(duration).await
+    }
+}
+
+/// Run a future with an optional timeout.
+/// Returns `None` when the timeout elapsed first (the future is then dropped).
+pub(crate) async fn timeout<F: Future>(duration: Option<Duration>, fut: F) -> Option<F::Output> {
+    let Some(duration) = duration else {
+        return Some(fut.await);
+    };
+
+    let mut fut = pin!(fut);
+    let mut delay = pin!(Delay::new(duration));
+
+    poll_fn(|cx| {
+        if let Poll::Ready(output) = fut.as_mut().poll(cx) {
+            return Poll::Ready(Some(output));
+        }
+        if delay.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(None);
+        }
+        Poll::Pending
+    })
+    .await
+}
@@ -0,0 +1,105 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Internal tracing helpers, compiled to nothing without the `tracing` feature.
+
+/// Emit a `tracing` event (e.g. `trace_event!(info, version = %v, "message")`).
+#[cfg(feature = "tracing")]
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {
+        tracing::$level!($($arg)+)
+    };
+}
+
+/// Emit a `tracing` event (no-op, the `tracing` feature is disabled).
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)+) => {};
+}
+
+pub(crate) use trace_event;
+
+use std::future::Future;
+
+use okerr::Result;
+
+use crate::{MigrationPlan, PlanStep, Version};
+
+/// A `tracing` span of a migration run or step
+/// (no-op when the `tracing` feature is disabled).
+pub(crate) struct TraceSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    /// Emit an error event on failure (only once, by the failing step).
+    #[cfg(feature = "tracing")]
+    error_event: bool,
+}
+
+impl TraceSpan {
+    /// Span of a migration run (`migrate_to`).
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn run<V: Version>(plan: &MigrationPlan<V>) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "migrate_to",
+                from = %plan.from,
+                target = %plan.target,
+                direction = ?plan.direction(),
+                steps = plan.steps.len(),
+                outcome = tracing::field::Empty,
+            ),
+            #[cfg(feature = "tracing")]
+            error_event: false,
+        }
+    }
+
+    /// Span of a migration step, child of the current run span.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn step<V: Version>(step: &PlanStep<V>) -> Self {
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::info_span!(
+                "migration_step",
+                version = %step.version,
+                name = %step.name,
+                direction = ?step.direction,
+                outcome = tracing::field::Empty,
+            ),
+            #[cfg(feature = "tracing")]
+            error_event: true,
+        }
+    }
+
+    /// Instrument a future with the span.
+    pub(crate) fn instrument<F: Future>(&self, fut: F) -> impl Future<Output = F::Output> {
+        #[cfg(feature = "tracing")]
+        return tracing::Instrument::instrument(fut, self.span.clone());
+
+        #[cfg(not(feature = "tracing"))]
+        fut
+    }
+
+    /// Record the outcome (`success` or `failure`) of the run or step,
+    /// with an error event on failure of a step.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn record_outcome(&self, result: &Result<()>) {
+        #[cfg(feature = "tracing")]
+        match result {
+            Ok(()) => {
+                self.span.record("outcome", "success");
+            }
+            Err(e) => {
+                self.span.record("outcome", "failure");
+                if self.error_event {
+                    self.span
+                        .in_scope(|| tracing::error!(error = %e, "migratex migration step failed"));
+                }
+            }
+        }
+    }
+}
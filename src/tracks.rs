@@ -0,0 +1,325 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+use async_trait::async_trait;
+use okerr::{Context, Result};
+
+use crate::{
+    Dependency, Direction, Metadata, Migratex, MigratexError, MigrationPlan, PlanStep, Version,
+};
+
+/// Track is an independent version line (e.g. a database, a cache directory, a config file),
+/// migrated by its own `Migratex`.
+/// It lets `Tracks` run tracks with different contexts and metadata stores together.
+#[async_trait]
+pub trait Track<V: Version = i32>: Send {
+    /// Get the current version of the track.
+    fn version(&self) -> V;
+
+    /// Get the most recent migration version of the track.
+    fn latest_version(&self) -> V;
+
+    /// Plan the steps to migrate the track to a specific target version.
+    fn plan(&self, target: V) -> Result<MigrationPlan<V>>;
+
+    /// Migrate the track to a specific target version.
+    async fn migrate_to(&mut self, target: V) -> Result<()>;
+}
+
+#[async_trait]
+impl<MigContext, M, V> Track<V> for Migratex<'_, '_, MigContext, M, V>
+where
+    MigContext: Send,
+    M: Metadata<V> + Send,
+    V: Version,
+{
+    fn version(&self) -> V {
+        self.metadata().version()
+    }
+
+    fn latest_version(&self) -> V {
+        Migratex::latest_version(self)
+    }
+
+    fn plan(&self, target: V) -> Result<MigrationPlan<V>> {
+        Migratex::plan(self, target)
+    }
+
+    async fn migrate_to(&mut self, target: V) -> Result<()> {
+        Migratex::migrate_to(self, target).await
+    }
+}
+
+/// A step of a `Tracks` run: a step of the plan of a track.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrackStep<V: Version = i32> {
+    /// The name of the track.
+    pub track: String,
+    /// The step of the plan of the track.
+    pub step: PlanStep<V>,
+}
+
+/// Tracks runs several named tracks (one `Migratex` per track, each with its own version line)
+/// in a defined order: the order they are added in to migrate up,
+/// the reverse order to migrate down.
+///
+/// The migrations can depend on other tracks (see `Migration::depends_on`):
+/// the steps of the tracks are then interleaved so each dependency runs first
+/// (see `schedule_latest`). The cycles and the dependencies that cannot be reached
+/// are rejected before running anything.
+/// A run stops at the first failing track (the error is reported with the track name).
+///
+/// Example:
+///
+/// ```rust,ignore
+/// use migratex::Tracks;
+///
+/// let db = Migratex::new(&mut db_ctx, &mut db_meta, db_migrations());
+/// let files = Migratex::new(&mut files_ctx, &mut files_meta, files_migrations());
+///
+/// Tracks::new()
+///     .with_track("db", db)
+///     .with_track("files", files)
+///     .migrate_to_latest()
+///     .await?;
+/// ```
+pub struct Tracks<'a, V: Version = i32> {
+    tracks: Vec<(String, Box<dyn Track<V> + 'a>)>,
+}
+
+impl<'a, V: Version> Tracks<'a, V> {
+    /// Create a new (empty) Tracks.
+    pub fn new() -> Self {
+        Self { tracks: Vec::new() }
+    }
+
+    /// Add a named track, run after the previous ones (to migrate up).
+    pub fn with_track(mut self, name: impl Into<String>, track: impl Track<V> + 'a) -> Self {
+        self.tracks.push((name.into(), Box::new(track)));
+        self
+    }
+
+    /// Get the names of the tracks, in order.
+    pub fn names(&self) -> Vec<&str> {
+        self.tracks.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// Get a track by name.
+    pub fn track(&self, name: &str) -> Option<&(dyn Track<V> + 'a)> {
+        self.tracks
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, track)| track.as_ref())
+    }
+
+    /// Plan the steps to migrate every track to its latest version, in order.
+    pub fn plan_latest(&self) -> Result<Vec<(String, MigrationPlan<V>)>> {
+        self.plan_all(|track| track.latest_version())
+    }
+
+    /// Order the steps to migrate every track to its latest version:
+    /// the tracks in order, each dependency first.
+    /// Fails on a dependency cycle (`MigratexError::DependencyCycle`)
+    /// or a dependency that cannot be reached (`MigratexError::UnsatisfiedDependency`).
+    pub fn schedule_latest(&self) -> Result<Vec<TrackStep<V>>> {
+        let order: Vec<_> = (0..self.tracks.len()).collect();
+        self.schedule(self.plan_latest()?, &order)
+    }
+
+    /// Order the steps to rollback every track (to version 0):
+    /// the tracks in reverse order, each dependent migration first.
+    pub fn schedule_zero(&self) -> Result<Vec<TrackStep<V>>> {
+        let order: Vec<_> = (0..self.tracks.len()).rev().collect();
+        self.schedule(self.plan_all(|_| V::zero())?, &order)
+    }
+
+    /// Migrate every track to its latest version (see `schedule_latest`).
+    pub async fn migrate_to_latest(&mut self) -> Result<()> {
+        let schedule = self.schedule_latest()?;
+        self.run(schedule).await
+    }
+
+    /// Rollback every track (to version 0), see `schedule_zero`.
+    pub async fn migrate_to_zero(&mut self) -> Result<()> {
+        let schedule = self.schedule_zero()?;
+        self.run(schedule).await
+    }
+
+    /// Plan the steps to migrate every track to a target version, in order.
+    fn plan_all(
+        &self,
+        target: impl Fn(&dyn Track<V>) -> V,
+    ) -> Result<Vec<(String, MigrationPlan<V>)>> {
+        self.tracks
+            .iter()
+            .map(|(name, track)| {
+                let plan = track
+                    .plan(target(track.as_ref()))
+                    .with_context(|| format!("track {name}"))?;
+                Ok((name.clone(), plan))
+            })
+            .collect()
+    }
+
+    /// Run the scheduled steps, each sequence of steps of a track at once.
+    async fn run(&mut self, schedule: Vec<TrackStep<V>>) -> Result<()> {
+        let mut steps = schedule.into_iter().peekable();
+
+        while let Some(first) = steps.next() {
+            let mut target = first.step.resulting_version;
+            while let Some(next) = steps.next_if(|s| s.track == first.track) {
+                target = next.step.resulting_version;
+            }
+
+            let (name, track) = self
+                .tracks
+                .iter_mut()
+                .find(|(name, _)| *name == first.track)
+                .expect("scheduled track");
+
+            track
+                .migrate_to(target)
+                .await
+                .with_context(|| format!("track {name}"))?;
+        }
+
+        Ok(())
+    }
+
+    /// Order the steps of the plans (a plan per track, in the tracks order),
+    /// preferring the tracks in `order` when several steps are ready.
+    fn schedule(
+        &self,
+        plans: Vec<(String, MigrationPlan<V>)>,
+        order: &[usize],
+    ) -> Result<Vec<TrackStep<V>>> {
+        // The steps to run before each step, as (track index, step index)
+        let mut before: Vec<Vec<Vec<(usize, usize)>>> = plans
+            .iter()
+            .map(|(_, plan)| vec![Vec::new(); plan.steps.len()])
+            .collect();
+
+        for (t, (name, plan)) in plans.iter().enumerate() {
+            for (i, step) in plan.steps.iter().enumerate() {
+                for dependency in &step.depends_on {
+                    let unsatisfied = |reason: String| -> okerr::Error {
+                        MigratexError::UnsatisfiedDependency {
+                            track: name.clone(),
+                            version: step.version.clone(),
+                            name: step.name.clone(),
+                            dependency: dependency.clone(),
+                            reason,
+                        }
+                        .into()
+                    };
+
+                    let Some(d) = self.tracks.iter().position(|(n, _)| *n == dependency.track)
+                    else {
+                        return Err(unsatisfied(format!(
+                            "the track {} is unknown",
+                            dependency.track
+                        )));
+                    };
+
+                    let dependency_steps = &plans[d].1.steps;
+
+                    match step.direction {
+                        // The dependency must be reached first
+                        Direction::Up => {
+                            if self.is_reached(d, &plans[d].1, dependency) {
+                                continue;
+                            }
+
+                            let reaching = dependency_steps.iter().position(|s| {
+                                s.direction == Direction::Up
+                                    && s.resulting_version >= dependency.version
+                            });
+
+                            match reaching {
+                                Some(j) => before[t][i].push((d, j)),
+                                None => {
+                                    let track = &self.tracks[d].1;
+                                    return Err(unsatisfied(format!(
+                                        "the track {} is at {} and its latest migration is {}",
+                                        dependency.track,
+                                        track.version(),
+                                        track.latest_version()
+                                    )));
+                                }
+                            }
+                        }
+                        // The dependency must be left only once the step is rolled back
+                        Direction::Down => {
+                            let leaving = dependency_steps.iter().position(|s| {
+                                s.direction == Direction::Down
+                                    && s.resulting_version < dependency.version
+                            });
+
+                            if let Some(j) = leaving {
+                                before[d][j].push((t, i));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        let mut next = vec![0; plans.len()];
+        let mut done: Vec<Vec<bool>> = before.iter().map(|b| vec![false; b.len()]).collect();
+        let mut schedule = Vec::new();
+
+        loop {
+            let ready = order.iter().copied().find(|&t| {
+                next[t] < before[t].len() && before[t][next[t]].iter().all(|&(d, j)| done[d][j])
+            });
+
+            let Some(t) = ready else {
+                break;
+            };
+
+            let i = next[t];
+            done[t][i] = true;
+            next[t] += 1;
+            schedule.push(TrackStep {
+                track: plans[t].0.clone(),
+                step: plans[t].1.steps[i].clone(),
+            });
+        }
+
+        let blocked: Vec<_> = order
+            .iter()
+            .filter(|&&t| next[t] < before[t].len())
+            .map(|&t| format!("{}/{}", plans[t].0, plans[t].1.steps[next[t]].version))
+            .collect();
+
+        if !blocked.is_empty() {
+            return Err(MigratexError::<V>::DependencyCycle { steps: blocked }.into());
+        }
+
+        Ok(schedule)
+    }
+
+    /// Whether a track is (and stays, during its plan) at the version of a dependency or above.
+    fn is_reached(
+        &self,
+        track: usize,
+        plan: &MigrationPlan<V>,
+        dependency: &Dependency<V>,
+    ) -> bool {
+        self.tracks[track].1.version() >= dependency.version
+            && plan
+                .steps
+                .iter()
+                .all(|s| s.resulting_version >= dependency.version)
+    }
+}
+
+impl<V: Version> Default for Tracks<'_, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
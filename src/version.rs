@@ -0,0 +1,95 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+use std::cmp::Ordering;
+use std::fmt::{Debug, Display};
+
+use okerr::{Context, Result};
+
+/// A migration version identifier.
+/// Versions are ordered: a migration with a greater version runs after the lower ones.
+///
+/// Implemented for `i32` (default), `i64` (e.g. timestamps like `20261017123045`),
+/// `String` and `semver::Version` (with the `semver` feature).
+///
+/// **A `String` version is compared lexicographically**, character by character:
+/// `"10.0.0" < "2.3.0"`. Use versions of the same width (zero-padded, e.g. `"002.003.000"`,
+/// or dates like `"2026-10-17"`), or `semver::Version` for numeric components.
+pub trait Version: Clone + Ord + Debug + Display + Send + Sync + 'static {
+    /// The version before the first migration (e.g. `0`).
+    fn zero() -> Self;
+
+    /// Encode the version to be stored (e.g. in a database column).
+    fn to_storage(&self) -> String {
+        self.to_string()
+    }
+
+    /// Decode a version encoded by `to_storage`.
+    fn from_storage(s: &str) -> Result<Self>;
+}
+
+impl Version for i32 {
+    fn zero() -> Self {
+        0
+    }
+
+    fn from_storage(s: &str) -> Result<Self> {
+        s.parse()
+            .with_context(|| format!("invalid i32 version: {}", s))
+    }
+}
+
+impl Version for i64 {
+    fn zero() -> Self {
+        0
+    }
+
+    fn from_storage(s: &str) -> Result<Self> {
+        s.parse()
+            .with_context(|| format!("invalid i64 version: {}", s))
+    }
+}
+
+/// Compared lexicographically (`"10.0.0" < "2.3.0"`):
+/// use zero-padded versions (e.g. `"002.003.000"`), or `semver::Version`.
+impl Version for String {
+    fn zero() -> Self {
+        String::new()
+    }
+
+    fn from_storage(s: &str) -> Result<Self> {
+        Ok(s.to_string())
+    }
+}
+
+#[cfg(feature = "semver")]
+impl Version for semver::Version {
+    fn zero() -> Self {
+        semver::Version::new(0, 0, 0)
+    }
+
+    fn from_storage(s: &str) -> Result<Self> {
+        semver::Version::parse(s).with_context(|| format!("invalid semver version: {}", s))
+    }
+}
+
+/// Compare two application versions (`MAJOR.MINOR.PATCH`, any number of numeric components,
+/// the pre-release and build suffixes are ignored).
+/// Returns `None` when one of them cannot be compared (e.g. empty, not numeric).
+pub(crate) fn cmp_app_versions(a: &str, b: &str) -> Option<Ordering> {
+    let parse = |s: &str| -> Option<Vec<u64>> {
+        let core = s.trim().trim_start_matches('v').split(['-', '+']).next()?;
+        core.split('.').map(|n| n.parse().ok()).collect()
+    };
+
+    let (mut a, mut b) = (parse(a)?, parse(b)?);
+    let len = a.len().max(b.len());
+    a.resize(len, 0);
+    b.resize(len, 0);
+
+    Some(a.cmp(&b))
+}
@@ -0,0 +1,155 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the app-version compatibility gating.
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use migratex::{AppVersionPolicy, Metadata, Migratex, MigratexError};
+use okerr::Result;
+
+use common::{TempDir, TestContext, TestMetadata, create_test_migrations};
+
+#[tokio::test]
+async fn test_app_version_recorded() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    // Unknown until a run
+    assert_eq!(meta.app_version(), "");
+
+    let mut mx =
+        Migratex::new(&mut ctx, &mut meta, create_test_migrations(2)).with_app_version("1.4.0");
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(meta.app_version(), "1.4.0");
+
+    // Also recorded when there is nothing to run
+    let mut mx =
+        Migratex::new(&mut ctx, &mut meta, create_test_migrations(2)).with_app_version("1.5.0");
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(meta.app_version(), "1.5.0");
+
+    meta.save(&path)?;
+    assert_eq!(TestMetadata::load_or_init(&path)?.app_version(), "1.5.0");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_app_version_refuses_older_binary() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx =
+        Migratex::new(&mut ctx, &mut meta, create_test_migrations(3)).with_app_version("2.1.0");
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    let mut mx =
+        Migratex::new(&mut ctx, &mut meta, create_test_migrations(3)).with_app_version("2.0.9");
+
+    let err = mx.migrate_to_latest().await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<MigratexError>(),
+        Some(MigratexError::NewerAppVersion { app_version, stored })
+            if app_version == "2.0.9" && stored == "2.1.0"
+    ));
+    assert!(mx.plan(0).is_err());
+    drop(mx);
+
+    // Nothing was run or recorded
+    assert_eq!(meta.version(), 3);
+    assert_eq!(meta.app_version(), "2.1.0");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_app_version_accepted() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+    meta.set_app_version("2.1.0".to_string());
+
+    // Ignored by the policy
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(1))
+        .with_app_version("2.0.0")
+        .with_app_version_policy(AppVersionPolicy::Ignore);
+    mx.migrate_to_latest().await?;
+    drop(mx);
+    assert_eq!(meta.app_version(), "2.0.0");
+
+    // Same release (pre-release and build suffixes ignored), newer, not comparable
+    for app_version in ["2.0.0+build.7", "2.0.0-rc.1", "2.10", "v3.0.0", "nightly"] {
+        let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(1))
+            .with_app_version(app_version);
+        mx.migrate_to_latest().await?;
+    }
+    assert_eq!(meta.app_version(), "nightly");
+
+    // Without app version, no gating
+    meta.set_app_version("99.0.0".to_string());
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(2));
+    mx.migrate_to_latest().await?;
+    drop(mx);
+    assert_eq!(meta.version(), 2);
+    assert_eq!(meta.app_version(), "99.0.0");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_app_version_legacy_value_ignored() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    // Written by a previous release: the version of migratex itself, not the app one
+    std::fs::write(
+        &path,
+        r#"{
+            "version": 2,
+            "app_version": "0.2.2",
+            "status": "Clean",
+            "created_at": "2026-01-01T00:00:00+00:00",
+            "updated_at": "2026-01-01T00:00:00+00:00"
+        }"#,
+    )?;
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    assert!(!meta.app_version_recorded());
+
+    // The first run of the app adopting the gating is accepted
+    let mut mx =
+        Migratex::new(&mut ctx, &mut meta, create_test_migrations(3)).with_app_version("0.1.0");
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 3);
+    assert_eq!(meta.app_version(), "0.1.0");
+    assert!(meta.app_version_recorded());
+
+    meta.save(&path)?;
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    assert!(meta.app_version_recorded());
+
+    // Then the recorded app version is checked
+    let mut mx =
+        Migratex::new(&mut ctx, &mut meta, create_test_migrations(3)).with_app_version("0.0.9");
+    assert!(mx.migrate_to_latest().await.is_err());
+
+    Ok(())
+}
@@ -0,0 +1,254 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the backup (snapshot / restore) of file-based data.
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use migratex::{Backup, BoxMigration, FileBackup, MetaStatus, Metadata, Migratex, Migration};
+use okerr::{Result, fail};
+
+use common::{TempDir, TestMetadata};
+
+/// Context pointing to a directory of user data.
+struct FilesContext {
+    dir: PathBuf,
+}
+
+/// Migration rewriting the files of the data directory (and failing if asked).
+struct RewriteMigration {
+    version: i32,
+    fail: bool,
+    tags: &'static [&'static str],
+}
+
+#[async_trait]
+impl Migration<FilesContext> for RewriteMigration {
+    fn version(&self) -> i32 {
+        self.version
+    }
+
+    fn tags(&self) -> &[&str] {
+        self.tags
+    }
+
+    async fn up(&self, ctx: &mut FilesContext) -> Result<()> {
+        fs::write(ctx.dir.join("a.txt"), format!("v{}", self.version))?;
+        fs::write(ctx.dir.join(format!("new_{}.txt", self.version)), "new")?;
+
+        if self.fail {
+            fail!("half-transformed");
+        }
+        Ok(())
+    }
+
+    async fn down(&self, _ctx: &mut FilesContext) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Create the data directory (with a file) and the backup of it.
+fn setup(temp: &TempDir) -> Result<(PathBuf, FileBackup)> {
+    let dir = temp.path().join("data");
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("a.txt"), "v0")?;
+
+    let backup = FileBackup::new(temp.path().join("backups"))
+        .with_path(&dir)
+        .with_path(temp.metadata_path());
+
+    Ok((dir, backup))
+}
+
+fn migrations(fail_at: i32) -> Vec<BoxMigration<FilesContext>> {
+    (1..=2)
+        .map(|version| {
+            Box::new(RewriteMigration {
+                version,
+                fail: version == fail_at,
+                tags: &[],
+            }) as BoxMigration<FilesContext>
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn test_backup_restore_on_failure() -> Result<()> {
+    let temp = TempDir::new()?;
+    let (dir, backup) = setup(&temp)?;
+
+    let mut ctx = FilesContext { dir: dir.clone() };
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations(2))
+        .with_backup(backup.clone())
+        .with_restore_on_failure(true);
+
+    assert!(mx.migrate_to_latest().await.is_err());
+    assert!(mx.last_snapshot().is_some());
+    drop(mx);
+
+    assert_eq!(fs::read_to_string(dir.join("a.txt"))?, "v0");
+    assert!(!dir.join("new_1.txt").exists());
+    assert!(!dir.join("new_2.txt").exists());
+
+    assert_eq!(meta.version(), 0);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+    assert!(meta.applied.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_backup_restore_puts_back_skipped() -> Result<()> {
+    let temp = TempDir::new()?;
+    let (dir, backup) = setup(&temp)?;
+
+    let mut ctx = FilesContext { dir };
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    // The migration 1 is skipped (not tagged "prod"), the migration 2 fails
+    let migrations: Vec<BoxMigration<FilesContext>> = vec![
+        Box::new(RewriteMigration {
+            version: 1,
+            fail: false,
+            tags: &["dev"],
+        }),
+        Box::new(RewriteMigration {
+            version: 2,
+            fail: true,
+            tags: &[],
+        }),
+    ];
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations)
+        .with_tags(["prod"])
+        .with_backup(backup)
+        .with_restore_on_failure(true);
+
+    assert!(mx.migrate_to_latest().await.is_err());
+    drop(mx);
+
+    assert_eq!(meta.version(), 0);
+    assert!(meta.applied.is_empty());
+    assert!(meta.skipped.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_backup_without_restore() -> Result<()> {
+    let temp = TempDir::new()?;
+    let (dir, backup) = setup(&temp)?;
+
+    let mut ctx = FilesContext { dir: dir.clone() };
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations(2)).with_backup(backup.clone());
+
+    assert!(mx.migrate_to_latest().await.is_err());
+    drop(mx);
+
+    assert_eq!(fs::read_to_string(dir.join("a.txt"))?, "v2");
+    assert_eq!(meta.version(), 1);
+    assert_eq!(meta.status(), MetaStatus::Failed);
+
+    // Restore on demand
+    let snapshot = backup.latest().await?.unwrap();
+    backup.restore(&snapshot).await?;
+
+    assert_eq!(fs::read_to_string(dir.join("a.txt"))?, "v0");
+    assert!(!dir.join("new_1.txt").exists());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_backup_restores_metadata_file() -> Result<()> {
+    let temp = TempDir::new()?;
+    let (dir, backup) = setup(&temp)?;
+
+    let mut ctx = FilesContext { dir };
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations(0)).with_backup(backup.clone());
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    meta.save(temp.metadata_path())?;
+    assert_eq!(
+        TestMetadata::load_or_init(temp.metadata_path())?.version(),
+        2
+    );
+
+    let snapshot = backup.latest().await?.unwrap();
+    backup.restore(&snapshot).await?;
+
+    assert_eq!(
+        TestMetadata::load_or_init(temp.metadata_path())?.version(),
+        0
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_backup_retention() -> Result<()> {
+    let temp = TempDir::new()?;
+    let (_, backup) = setup(&temp)?;
+    let backup = backup.with_keep(2);
+
+    let first = backup.snapshot().await?;
+    let second = backup.snapshot().await?;
+    let third = backup.snapshot().await?;
+
+    let snapshots = backup.snapshots().await?;
+    assert_eq!(snapshots, vec![second, third]);
+    assert!(!first.path.exists());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_backup_nothing_to_run() -> Result<()> {
+    let temp = TempDir::new()?;
+    let (dir, backup) = setup(&temp)?;
+
+    let mut ctx = FilesContext { dir };
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations(0)).with_backup(backup.clone());
+    mx.migrate_to_zero().await?;
+
+    assert!(mx.last_snapshot().is_none());
+    assert!(backup.snapshots().await?.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_backup_dir_inside_snapshotted_path() -> Result<()> {
+    let temp = TempDir::new()?;
+    let dir = temp.path().join("data");
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join("a.txt"), "v0")?;
+
+    // The snapshot would be copied into itself
+    let backup = FileBackup::new(dir.join(".backups")).with_path(&dir);
+    let err = backup.snapshot().await.unwrap_err();
+
+    assert!(err.to_string().contains("is inside the snapshotted path"));
+    assert!(backup.snapshots().await?.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,90 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the blocking facade (no async runtime).
+
+mod common;
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use migratex::testing::MemoryMetadata;
+use migratex::{Backoff, BoxMigration, MetaStatus, Metadata, Migratex, Migration, RetryPolicy};
+use okerr::{Result, fail};
+
+use common::{TestContext, create_test_migrations};
+
+/// Migration failing on its first attempt (retryable).
+struct Flaky {
+    attempts: AtomicU32,
+}
+
+#[async_trait]
+impl Migration<TestContext> for Flaky {
+    fn version(&self) -> i32 {
+        4
+    }
+
+    fn retryable(&self) -> bool {
+        true
+    }
+
+    async fn up(&self, ctx: &mut TestContext) -> Result<()> {
+        if self.attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+            fail!("database is locked");
+        }
+        ctx.record_up(4);
+        Ok(())
+    }
+
+    async fn down(&self, ctx: &mut TestContext) -> Result<()> {
+        ctx.record_down(4);
+        Ok(())
+    }
+}
+
+#[test]
+fn test_blocking_migrate() -> Result<()> {
+    let mut ctx = TestContext::new();
+    let mut meta = MemoryMetadata::default();
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(3));
+    mx.blocking().migrate_to_latest()?;
+    mx.blocking().migrate_prev()?;
+    mx.blocking().redo()?;
+    drop(mx);
+
+    assert_eq!(ctx.applied_migrations, vec![1, 2]);
+    assert_eq!(meta.version(), 2);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+
+    Ok(())
+}
+
+#[test]
+fn test_blocking_retry_waits() -> Result<()> {
+    let mut ctx = TestContext::new();
+    let mut meta = MemoryMetadata::default();
+
+    let mut migrations: Vec<BoxMigration<TestContext>> = create_test_migrations(3);
+    migrations.push(Box::new(Flaky {
+        attempts: AtomicU32::new(0),
+    }));
+
+    // The backoff delay completes without runtime
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations)
+        .with_retry(RetryPolicy::new(2).with_backoff(Backoff::Fixed(Duration::from_millis(20))))
+        .with_step_timeout(Duration::from_secs(5));
+    mx.blocking().migrate_to_latest()?;
+    drop(mx);
+
+    assert_eq!(ctx.applied_migrations, vec![1, 2, 3, 4]);
+    assert_eq!(meta.version(), 4);
+
+    Ok(())
+}
@@ -0,0 +1,149 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the Migratex builder.
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use std::time::Duration;
+
+use migratex::{Metadata, Migratex, MigratexError, OutOfOrder};
+use okerr::Result;
+
+use common::{TempDir, TestContext, TestMetadata, TestMigration, create_test_migrations};
+
+fn invalid_reason(result: Result<impl Sized>) -> String {
+    match result {
+        Err(e) => match e.downcast_ref::<MigratexError>() {
+            Some(MigratexError::InvalidConfig { reason }) => reason.clone(),
+            _ => panic!("unexpected error: {e}"),
+        },
+        Ok(_) => panic!("unexpected valid configuration"),
+    }
+}
+
+#[tokio::test]
+async fn test_builder_borrowed() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::builder()
+        .with_context(&mut ctx)
+        .with_metadata(&mut meta)
+        .with_migrations(create_test_migrations(2))
+        .with_migration(TestMigration::new(3, "m3"))
+        .with_out_of_order(OutOfOrder::Apply)
+        .with_step_timeout(Duration::from_secs(5))
+        .with_app_version("1.0.0")
+        .build()?;
+
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(ctx.applied_migrations, vec![1, 2, 3]);
+    assert_eq!(meta.version(), 3);
+    assert_eq!(meta.app_version(), "1.0.0");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_builder_owned() -> Result<()> {
+    let temp = TempDir::new()?;
+
+    let mut mx = Migratex::builder()
+        .with_owned_context(TestContext::new())
+        .with_owned_metadata(TestMetadata::load_or_init(temp.metadata_path())?)
+        .with_migrations(create_test_migrations(2))
+        .build()?;
+
+    mx.migrate_to_latest().await?;
+
+    let (Some(ctx), Some(meta)) = mx.into_parts() else {
+        panic!("owned parts");
+    };
+    assert_eq!(ctx.applied_migrations, vec![1, 2]);
+    assert_eq!(meta.version(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_builder_mixed_ownership() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::builder()
+        .with_owned_context(TestContext::new())
+        .with_metadata(&mut meta)
+        .with_migrations(create_test_migrations(2))
+        .build()?;
+
+    mx.migrate_to_latest().await?;
+
+    // The owned context is given back, the borrowed metadata is not
+    let (ctx, owned_meta) = mx.into_parts();
+    assert_eq!(ctx.expect("owned context").applied_migrations, vec![1, 2]);
+    assert!(owned_meta.is_none());
+    assert_eq!(meta.version(), 2);
+
+    Ok(())
+}
+
+#[test]
+fn test_builder_validation() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let reason = invalid_reason(
+        Migratex::<TestContext, TestMetadata>::builder()
+            .with_metadata(&mut meta)
+            .build(),
+    );
+    assert_eq!(reason, "the context is missing");
+
+    let reason = invalid_reason(
+        Migratex::<TestContext, TestMetadata>::builder()
+            .with_context(&mut ctx)
+            .build(),
+    );
+    assert_eq!(reason, "the metadata is missing");
+
+    let reason = invalid_reason(
+        Migratex::builder()
+            .with_context(&mut ctx)
+            .with_metadata(&mut meta)
+            .with_migrations(create_test_migrations(2))
+            .with_migration(TestMigration::new(2, "again"))
+            .build(),
+    );
+    assert_eq!(reason, "several migrations have the version 2");
+
+    let reason = invalid_reason(
+        Migratex::builder()
+            .with_context(&mut ctx)
+            .with_metadata(&mut meta)
+            .with_timeout(Duration::ZERO)
+            .build(),
+    );
+    assert_eq!(reason, "a timeout cannot be zero");
+
+    let reason = invalid_reason(
+        Migratex::builder()
+            .with_context(&mut ctx)
+            .with_metadata(&mut meta)
+            .with_restore_on_failure(true)
+            .build(),
+    );
+    assert_eq!(reason, "restore on failure requires a backup");
+
+    Ok(())
+}
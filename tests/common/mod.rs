@@ -8,20 +8,28 @@
 // -- Common test utilities and helpers for Migratex tests.
 
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
-use migratex::Migration;
+use migratex::{Dependency, Migration};
 use okerr::Result;
 
 /// Test metadata - using JsonMetadata directly
 #[cfg(feature = "json")]
+#[allow(dead_code)]
 pub type TestMetadata = migratex::JsonMetadata;
 
+/// Log of the steps run, shared between the contexts of several tracks
+pub type EventLog = Arc<Mutex<Vec<String>>>;
+
 /// Test migration context that tracks applied migrations
 #[derive(Debug, Default, Clone)]
 pub struct TestContext {
     pub applied_migrations: Vec<i32>,
     pub should_fail_at_version: Option<i32>,
+    /// Track prefixed to the logged steps (`up core/1` instead of `up 1`)
+    pub track: Option<&'static str>,
+    pub log: EventLog,
 }
 
 impl TestContext {
@@ -33,17 +41,42 @@ impl TestContext {
     #[allow(dead_code)]
     pub fn with_fail_at(version: i32) -> Self {
         Self {
-            applied_migrations: Vec::new(),
             should_fail_at_version: Some(version),
+            ..Self::default()
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn in_track(track: &'static str, log: &EventLog) -> Self {
+        Self {
+            track: Some(track),
+            log: log.clone(),
+            ..Self::default()
         }
     }
 
     pub fn record_up(&mut self, version: i32) {
         self.applied_migrations.push(version);
+        self.record_event("up", version);
     }
 
     pub fn record_down(&mut self, version: i32) {
         self.applied_migrations.retain(|&v| v != version);
+        self.record_event("down", version);
+    }
+
+    fn record_event(&self, step: &str, version: i32) {
+        let event = match self.track {
+            Some(track) => format!("{} {}/{}", step, track, version),
+            None => format!("{} {}", step, version),
+        };
+        self.log.lock().unwrap().push(event);
+    }
+
+    /// Steps run, in order
+    #[allow(dead_code)]
+    pub fn events(&self) -> Vec<String> {
+        self.log.lock().unwrap().clone()
     }
 
     #[allow(dead_code)]
@@ -57,6 +90,10 @@ pub struct TestMigration {
     pub version: i32,
     #[allow(dead_code)]
     name: String,
+    reversible: bool,
+    squashed_from: Option<i32>,
+    tags: &'static [&'static str],
+    depends_on: Vec<Dependency>,
 }
 
 #[allow(dead_code)]
@@ -65,8 +102,32 @@ impl TestMigration {
         Self {
             version,
             name: name.into(),
+            reversible: true,
+            squashed_from: None,
+            tags: &[],
+            depends_on: Vec::new(),
         }
     }
+
+    pub fn irreversible(mut self) -> Self {
+        self.reversible = false;
+        self
+    }
+
+    pub fn squashing(mut self, from: i32) -> Self {
+        self.squashed_from = Some(from);
+        self
+    }
+
+    pub fn with_tags(mut self, tags: &'static [&'static str]) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    pub fn depending_on(mut self, dependency: Dependency) -> Self {
+        self.depends_on.push(dependency);
+        self
+    }
 }
 
 #[async_trait]
@@ -75,6 +136,26 @@ impl Migration<TestContext> for TestMigration {
         self.version
     }
 
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn reversible(&self) -> bool {
+        self.reversible
+    }
+
+    fn squashed_from(&self) -> Option<i32> {
+        self.squashed_from
+    }
+
+    fn tags(&self) -> &[&str] {
+        self.tags
+    }
+
+    fn depends_on(&self) -> Vec<Dependency> {
+        self.depends_on.clone()
+    }
+
     async fn up(&self, ctx: &mut TestContext) -> Result<()> {
         if ctx.should_fail_at_version == Some(self.version) {
             okerr::fail!("Intentional failure at version {}", self.version);
@@ -98,6 +179,7 @@ pub struct TempDir {
 }
 
 impl TempDir {
+    #[allow(dead_code)]
     pub fn new() -> Result<Self> {
         let path = std::env::temp_dir().join(format!("migratex_test_{}", uuid::Uuid::new_v4()));
         std::fs::create_dir_all(&path)?;
@@ -109,6 +191,7 @@ impl TempDir {
         &self.path
     }
 
+    #[allow(dead_code)]
     pub fn metadata_path(&self) -> PathBuf {
         self.path.join("metadata.json")
     }
@@ -131,6 +214,7 @@ pub fn create_test_migrations(count: i32) -> Vec<migratex::BoxMigration<TestCont
         .collect()
 }
 
+#[allow(dead_code)]
 mod uuid {
     use std::sync::atomic::{AtomicU64, Ordering};
 
@@ -0,0 +1,76 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the data ahead of the binary (version > latest known migration).
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use migratex::{DataAhead, MetaStatus, Metadata, Migratex, MigratexError};
+use okerr::Result;
+
+use common::{TempDir, TestContext, TestMetadata, create_test_migrations};
+
+#[tokio::test]
+async fn test_data_ahead_refused() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    // A newer release migrated the data up to 12
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(12));
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    // The rolled back binary only knows up to 10
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(10));
+
+    let err = mx.migrate_to_latest().await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<MigratexError>(),
+        Some(MigratexError::DataAhead {
+            version: 12,
+            latest: 10
+        })
+    ));
+
+    // Any target, nothing runs
+    assert!(mx.migrate_to(5).await.is_err());
+    drop(mx);
+
+    assert_eq!(meta.version(), 12);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+    assert_eq!(ctx.applied_migrations.len(), 12);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_data_ahead_ignored() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(12));
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(10))
+        .with_data_ahead(DataAhead::Ignore);
+
+    assert!(mx.plan(10)?.is_empty());
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    // The unknown versions are left as is
+    assert_eq!(meta.version(), 12);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+    assert_eq!(ctx.applied_migrations.len(), 12);
+
+    Ok(())
+}
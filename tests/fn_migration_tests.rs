@@ -0,0 +1,122 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for closure-based migrations (FnMigration).
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use migratex::{BoxMigration, FnMigration, MetaStatus, Metadata, Migratex, Migration};
+use okerr::Result;
+
+use common::{TempDir, TestContext, TestMetadata, TestMigration};
+
+/// Create a closure-based migration recording its execution in the context.
+fn fn_migration(version: i32) -> FnMigration<TestContext> {
+    FnMigration::new(
+        version,
+        format!("fn_migration_{}", version),
+        move |ctx: &mut TestContext| {
+            Box::pin(async move {
+                ctx.record_up(version);
+                Ok(())
+            })
+        },
+        move |ctx: &mut TestContext| {
+            Box::pin(async move {
+                ctx.record_down(version);
+                Ok(())
+            })
+        },
+    )
+}
+
+#[test]
+fn test_fn_migration_version_and_name() {
+    let m = fn_migration(7);
+
+    assert_eq!(m.version(), 7);
+    assert_eq!(m.name(), "fn_migration_7");
+}
+
+#[tokio::test]
+async fn test_fn_migration_mixed_with_struct_migrations() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    let migrations: Vec<BoxMigration<TestContext>> = vec![
+        Box::new(TestMigration::new(1, "struct_1")),
+        Box::new(fn_migration(2)),
+        Box::new(TestMigration::new(3, "struct_3")),
+    ];
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations);
+
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 3);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+    assert_eq!(ctx.applied_migrations, vec![1, 2, 3]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fn_migration_down() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    let migrations: Vec<BoxMigration<TestContext>> =
+        vec![Box::new(fn_migration(1)), Box::new(fn_migration(2))];
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations);
+
+    mx.migrate_to_latest().await?;
+    mx.migrate_to(1).await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 1);
+    assert_eq!(ctx.applied_migrations, vec![1]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fn_migration_failure_marks_failed() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    let migrations: Vec<BoxMigration<TestContext>> = vec![
+        Box::new(fn_migration(1)),
+        Box::new(FnMigration::new(
+            2,
+            "failing",
+            |_ctx| Box::pin(async move { okerr::err!("Intentional failure") }),
+            |_ctx| Box::pin(async move { Ok(()) }),
+        )),
+    ];
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations);
+
+    let result = mx.migrate_to_latest().await;
+    drop(mx);
+
+    assert!(result.is_err());
+    assert_eq!(meta.status(), MetaStatus::Failed);
+    assert_eq!(meta.version(), 1);
+    assert_eq!(ctx.applied_migrations, vec![1]);
+
+    Ok(())
+}
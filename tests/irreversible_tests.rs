@@ -0,0 +1,163 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for migration plans and irreversible migrations.
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use migratex::{BoxMigration, Direction, MetaStatus, Metadata, Migratex, MigratexError};
+use okerr::Result;
+
+use common::{TempDir, TestContext, TestMetadata, TestMigration, create_test_migrations};
+
+/// Migrations 1..=4, where 3 is irreversible.
+fn migrations_with_irreversible() -> Vec<BoxMigration<TestContext>> {
+    vec![
+        Box::new(TestMigration::new(1, "m1")),
+        Box::new(TestMigration::new(2, "m2")),
+        Box::new(TestMigration::new(3, "m3").irreversible()),
+        Box::new(TestMigration::new(4, "m4")),
+    ]
+}
+
+#[tokio::test]
+async fn test_plan_up() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    meta.set_version(1);
+
+    let mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(5));
+    let plan = mx.plan(4)?;
+
+    assert_eq!(plan.from, 1);
+    assert_eq!(plan.direction(), Direction::Up);
+    assert_eq!(
+        plan.steps.iter().map(|s| s.version).collect::<Vec<_>>(),
+        vec![2, 3, 4]
+    );
+    assert_eq!(plan.resulting_version(), 4);
+
+    // Nothing is run
+    assert!(ctx.applied_migrations.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_plan_down() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    meta.set_version(5);
+
+    let mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(5));
+    let plan = mx.plan(2)?;
+
+    assert_eq!(plan.direction(), Direction::Down);
+    assert_eq!(
+        plan.steps.iter().map(|s| s.version).collect::<Vec<_>>(),
+        vec![5, 4, 3]
+    );
+    assert!(plan.steps.iter().all(|s| s.direction == Direction::Down));
+    assert_eq!(plan.resulting_version(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_irreversible_up_is_allowed() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations_with_irreversible());
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 4);
+    assert_eq!(ctx.applied_migrations, vec![1, 2, 3, 4]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_irreversible_down_is_refused_up_front() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations_with_irreversible());
+    mx.migrate_to_latest().await?;
+
+    assert!(mx.plan(1).is_err());
+
+    let err = mx.migrate_to(1).await.unwrap_err();
+    drop(mx);
+
+    assert!(matches!(
+        err.downcast_ref::<MigratexError>(),
+        Some(MigratexError::Irreversible { version: 3, .. })
+    ));
+
+    // No down has run, metadata is untouched
+    assert_eq!(meta.version(), 4);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+    assert_eq!(ctx.applied_migrations, vec![1, 2, 3, 4]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_down_above_irreversible_is_allowed() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations_with_irreversible());
+    mx.migrate_to_latest().await?;
+    mx.migrate_to(3).await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 3);
+    assert_eq!(ctx.applied_migrations, vec![1, 2, 3]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_irreversible_down_when_forced() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations_with_irreversible())
+        .with_force_irreversible(true);
+    mx.migrate_to_latest().await?;
+    mx.migrate_to(1).await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 1);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+    assert_eq!(ctx.applied_migrations, vec![1]);
+
+    Ok(())
+}
@@ -288,3 +288,52 @@ fn test_json_store_concurrent_access() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_json_store_tracks() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    meta.set_version(1);
+    meta.save(&path)?;
+
+    let mut db = TestMetadata::load_or_init_track(&path, "db")?;
+    assert_eq!(db.track(), Some("db"));
+    assert_eq!(db.version(), 0);
+    db.set_version(3);
+    db.save(&path)?;
+
+    let mut files = TestMetadata::load_or_init_track(&path, "files")?;
+    files.set_version(7);
+    files.mark_failed();
+    files.save(&path)?;
+
+    // Saving the default track preserves the named ones
+    meta.set_version(2);
+    meta.save(&path)?;
+
+    assert_eq!(TestMetadata::load_or_init(&path)?.version(), 2);
+    assert_eq!(TestMetadata::load_or_init_track(&path, "db")?.version(), 3);
+
+    let files = TestMetadata::load_or_init_track(&path, "files")?;
+    assert_eq!(files.version(), 7);
+    assert_eq!(files.status(), MetaStatus::Failed);
+
+    Ok(())
+}
+
+#[test]
+fn test_json_store_track_in_new_file() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut db = TestMetadata::load_or_init_track(&path, "db")?;
+    db.set_version(3);
+    db.save(&path)?;
+
+    assert_eq!(TestMetadata::load_or_init(&path)?.version(), 0);
+    assert_eq!(TestMetadata::load_or_init_track(&path, "db")?.version(), 3);
+
+    Ok(())
+}
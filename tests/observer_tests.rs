@@ -0,0 +1,177 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the migration observers.
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use migratex::{Direction, Migratex, MigrationObserver, MigrationPlan, PlanStep};
+use okerr::{Error, Result};
+
+use common::{TempDir, TestContext, TestMetadata, create_test_migrations};
+
+/// Observer recording the events.
+#[derive(Default)]
+struct RecordingObserver {
+    events: Mutex<Vec<String>>,
+}
+
+impl RecordingObserver {
+    fn events(&self) -> Vec<String> {
+        self.events.lock().unwrap().clone()
+    }
+
+    fn push(&self, event: String) {
+        self.events.lock().unwrap().push(event);
+    }
+}
+
+fn direction_str(direction: Direction) -> &'static str {
+    match direction {
+        Direction::Up => "up",
+        Direction::Down => "down",
+    }
+}
+
+impl MigrationObserver for RecordingObserver {
+    fn before_run(&self, plan: &MigrationPlan) {
+        self.push(format!("before_run {}->{}", plan.from, plan.target));
+    }
+
+    fn before_step(&self, step: &PlanStep) {
+        self.push(format!(
+            "before_step {} {}",
+            direction_str(step.direction),
+            step.version
+        ));
+    }
+
+    fn after_step(&self, step: &PlanStep, _duration: Duration) {
+        self.push(format!(
+            "after_step {} {}",
+            direction_str(step.direction),
+            step.version
+        ));
+    }
+
+    fn on_error(&self, step: &PlanStep, error: &Error, _duration: Duration) {
+        self.push(format!("on_error {} {}", step.version, error));
+    }
+
+    fn after_run(&self, _plan: &MigrationPlan, result: &Result<()>, _duration: Duration) {
+        self.push(format!("after_run ok={}", result.is_ok()));
+    }
+}
+
+#[tokio::test]
+async fn test_observer_up_events() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let observer = Arc::new(RecordingObserver::default());
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(2))
+        .with_observer(observer.clone());
+    mx.migrate_to_latest().await?;
+
+    assert_eq!(
+        observer.events(),
+        vec![
+            "before_run 0->2",
+            "before_step up 1",
+            "after_step up 1",
+            "before_step up 2",
+            "after_step up 2",
+            "after_run ok=true",
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_observer_down_events() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let observer = Arc::new(RecordingObserver::default());
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(2));
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(2))
+        .with_observer(observer.clone());
+    mx.migrate_prev().await?;
+
+    assert_eq!(
+        observer.events(),
+        vec![
+            "before_run 2->1",
+            "before_step down 2",
+            "after_step down 2",
+            "after_run ok=true",
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_observer_error_events() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let observer = Arc::new(RecordingObserver::default());
+    let mut ctx = TestContext::with_fail_at(2);
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(3))
+        .with_observer(observer.clone());
+    assert!(mx.migrate_to_latest().await.is_err());
+
+    assert_eq!(
+        observer.events(),
+        vec![
+            "before_run 0->3",
+            "before_step up 1",
+            "after_step up 1",
+            "before_step up 2",
+            "on_error 2 Intentional failure at version 2",
+            "after_run ok=false",
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_observer_not_called_when_nothing_to_run() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let observer = Arc::new(RecordingObserver::default());
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(0))
+        .with_observer(observer.clone());
+    mx.migrate_to_latest().await?;
+
+    assert!(observer.events().is_empty());
+
+    Ok(())
+}
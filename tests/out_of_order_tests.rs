@@ -0,0 +1,210 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the applied-set model and the out-of-order migrations.
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use std::collections::BTreeSet;
+
+use migratex::{BoxMigration, MetaStatus, Metadata, Migratex, MigratexError, OutOfOrder};
+use okerr::Result;
+
+use common::{TempDir, TestContext, TestMetadata, TestMigration, create_test_migrations};
+
+/// Create test migrations with the given versions.
+fn migrations_of(versions: &[i32]) -> Vec<BoxMigration<TestContext>> {
+    versions
+        .iter()
+        .map(|&v| {
+            Box::new(TestMigration::new(v, format!("Migration_{}", v))) as BoxMigration<TestContext>
+        })
+        .collect()
+}
+
+/// Deploy the versions 1, 2 and 4, then return the context and the metadata.
+async fn deploy_without_3(path: &std::path::Path) -> Result<(TestContext, TestMetadata)> {
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(path)?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations_of(&[1, 2, 4]));
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    Ok((ctx, meta))
+}
+
+#[tokio::test]
+async fn test_applied_versions_are_recorded() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let (_ctx, meta) = deploy_without_3(&path).await?;
+
+    assert_eq!(meta.version(), 4);
+    assert_eq!(meta.applied, BTreeSet::from([1, 2, 4]));
+
+    // Persisted
+    meta.save(&path)?;
+    let loaded = TestMetadata::load_or_init(&path)?;
+    assert_eq!(loaded.applied, BTreeSet::from([1, 2, 4]));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_out_of_order_is_reported_by_default() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let (mut ctx, mut meta) = deploy_without_3(&path).await?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations_of(&[1, 2, 3, 4, 5]));
+    assert_eq!(mx.missing_versions(), vec![3]);
+
+    let err = mx.migrate_to_latest().await.unwrap_err();
+    drop(mx);
+
+    assert!(matches!(
+        err.downcast_ref::<MigratexError>(),
+        Some(MigratexError::OutOfOrder { versions, current: 4 }) if versions == &vec![3]
+    ));
+    assert_eq!(meta.version(), 4);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+    assert_eq!(ctx.applied_migrations, vec![1, 2, 4]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_out_of_order_apply() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let (mut ctx, mut meta) = deploy_without_3(&path).await?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations_of(&[1, 2, 3, 4, 5]))
+        .with_out_of_order(OutOfOrder::Apply);
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 5);
+    assert_eq!(meta.applied, BTreeSet::from([1, 2, 3, 4, 5]));
+    assert_eq!(ctx.applied_migrations, vec![1, 2, 4, 3, 5]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_out_of_order_apply_at_latest() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let (mut ctx, mut meta) = deploy_without_3(&path).await?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations_of(&[1, 2, 3, 4]))
+        .with_out_of_order(OutOfOrder::Apply);
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 4);
+    assert_eq!(ctx.applied_migrations, vec![1, 2, 4, 3]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_out_of_order_ignore() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let (mut ctx, mut meta) = deploy_without_3(&path).await?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations_of(&[1, 2, 3, 4, 5]))
+        .with_out_of_order(OutOfOrder::Ignore);
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 5);
+    assert_eq!(ctx.applied_migrations, vec![1, 2, 4, 5]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_down_skips_never_applied_migrations() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let (mut ctx, mut meta) = deploy_without_3(&path).await?;
+    ctx.should_fail_at_version = Some(3);
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations_of(&[1, 2, 3, 4]));
+    mx.migrate_to(1).await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 1);
+    assert_eq!(meta.applied, BTreeSet::from([1]));
+    assert_eq!(ctx.applied_migrations, vec![1]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_legacy_metadata_applied_set_is_filled() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    // Metadata saved before the applied-set model
+    meta.set_version(3);
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(5));
+    assert!(mx.missing_versions().is_empty());
+
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 5);
+    assert_eq!(meta.applied, BTreeSet::from([1, 2, 3, 4, 5]));
+    assert_eq!(ctx.applied_migrations, vec![4, 5]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sparse_versions_next_and_prev() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    let mut mx = Migratex::new(
+        &mut ctx,
+        &mut meta,
+        migrations_of(&[1_700_000_000, 1_700_000_500, 1_700_001_000]),
+    );
+
+    mx.migrate_next().await?;
+    mx.migrate_next().await?;
+    assert_eq!(mx.metadata().version(), 1_700_000_500);
+
+    mx.migrate_prev().await?;
+    assert_eq!(mx.metadata().version(), 1_700_000_000);
+
+    mx.migrate_prev().await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 0);
+    assert!(ctx.applied_migrations.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,63 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the owned Migratex (owned context and metadata).
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use migratex::{MetaStatus, Metadata, Migratex, OwnedMigratex};
+use okerr::Result;
+
+use common::{TempDir, TestContext, TestMetadata, create_test_migrations};
+
+/// Build a runner owning its context and metadata (returned by a function).
+fn runner(meta: TestMetadata) -> OwnedMigratex<TestContext, TestMetadata> {
+    Migratex::owned(TestContext::new(), meta, create_test_migrations(3))
+}
+
+#[tokio::test]
+async fn test_owned_spawned() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+    let mut mx = runner(TestMetadata::load_or_init(&path)?);
+
+    let handle = tokio::spawn(async move {
+        mx.migrate_to_latest().await?;
+        Ok::<_, okerr::Error>(mx)
+    });
+
+    let mx = handle.await??;
+    assert_eq!(mx.metadata().version(), 3);
+
+    let (Some(ctx), Some(meta)) = mx.into_parts() else {
+        panic!("owned parts");
+    };
+    assert_eq!(ctx.applied_migrations, vec![1, 2, 3]);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+
+    meta.save(&path)?;
+    assert_eq!(TestMetadata::load_or_init(&path)?.version(), 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_borrowed_has_no_parts() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(2));
+    mx.migrate_to_latest().await?;
+    assert!(matches!(mx.into_parts(), (None, None)));
+
+    assert_eq!(meta.version(), 2);
+
+    Ok(())
+}
@@ -0,0 +1,191 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the progress reporting.
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use migratex::{BoxMigration, FnMigration, Migratex, Progress, ProgressUpdate};
+use okerr::Result;
+
+use common::{TempDir, TestMetadata};
+
+/// Context giving the progress to the migrations.
+struct ProgressContext {
+    progress: Progress,
+}
+
+/// Create a migration processing `items` items, reporting its progress.
+fn items_migration(version: i32, items: u64) -> FnMigration<ProgressContext> {
+    FnMigration::new(
+        version,
+        format!("items_{}", version),
+        move |ctx: &mut ProgressContext| {
+            Box::pin(async move {
+                ctx.progress.set_total(items);
+                for _ in 0..items {
+                    ctx.progress.inc(1);
+                }
+                ctx.progress.message("done");
+                Ok(())
+            })
+        },
+        |_ctx: &mut ProgressContext| Box::pin(async { Ok(()) }),
+    )
+}
+
+#[tokio::test]
+async fn test_progress_callback() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let progress = Progress::new();
+    let updates: Arc<Mutex<Vec<ProgressUpdate>>> = Arc::default();
+    let recorded = Arc::clone(&updates);
+    progress.on_update(move |u| recorded.lock().unwrap().push(u.clone()));
+
+    let mut ctx = ProgressContext {
+        progress: progress.clone(),
+    };
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    let migrations: Vec<BoxMigration<ProgressContext>> = vec![
+        Box::new(items_migration(1, 2)),
+        Box::new(items_migration(2, 4)),
+    ];
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations).with_progress(progress.clone());
+    mx.migrate_to_latest().await?;
+
+    let updates = updates.lock().unwrap();
+    let summary: Vec<_> = updates
+        .iter()
+        .map(|u| {
+            format!(
+                "{}/{} v{} {}/{:?}",
+                u.step, u.steps, u.version, u.done, u.total
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        summary,
+        vec![
+            "1/2 v1 0/None",
+            "1/2 v1 0/Some(2)",
+            "1/2 v1 1/Some(2)",
+            "1/2 v1 2/Some(2)",
+            "1/2 v1 2/Some(2)",
+            "1/2 v1 2/Some(2)",
+            "2/2 v2 0/None",
+            "2/2 v2 0/Some(4)",
+            "2/2 v2 1/Some(4)",
+            "2/2 v2 2/Some(4)",
+            "2/2 v2 3/Some(4)",
+            "2/2 v2 4/Some(4)",
+            "2/2 v2 4/Some(4)",
+            "2/2 v2 4/Some(4)",
+        ]
+    );
+
+    // Message and end of the first step
+    assert_eq!(updates[4].message.as_deref(), Some("done"));
+    assert!(!updates[4].finished);
+    assert!(updates[5].finished);
+
+    // Overall progress
+    assert_eq!(updates[0].overall_fraction(), 0.0);
+    assert_eq!(updates[2].overall_fraction(), 0.25);
+    assert_eq!(updates[5].overall_fraction(), 0.5);
+    assert_eq!(updates[9].overall_fraction(), 0.75);
+    assert_eq!(updates.last().unwrap().overall_fraction(), 1.0);
+
+    // Nothing is running anymore
+    assert!(progress.current().is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_progress_subscribe() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let progress = Progress::new();
+    let rx = progress.subscribe();
+
+    let mut ctx = ProgressContext {
+        progress: progress.clone(),
+    };
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    let migrations: Vec<BoxMigration<ProgressContext>> = vec![Box::new(items_migration(1, 3))];
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations).with_progress(progress.clone());
+    mx.migrate_to_latest().await?;
+
+    let updates: Vec<_> = rx.try_iter().collect();
+
+    assert_eq!(updates.len(), 7);
+    assert_eq!(updates[0].name, "items_1");
+    assert_eq!(updates[3].step_fraction(), Some(2.0 / 3.0));
+    assert!(updates.last().unwrap().finished);
+
+    Ok(())
+}
+
+#[test]
+fn test_progress_ignored_outside_a_step() {
+    let progress = Progress::<i32>::new();
+    let rx = progress.subscribe();
+
+    progress.set_total(10);
+    progress.inc(1);
+    progress.message("ignored");
+
+    assert!(progress.current().is_none());
+    assert!(rx.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn test_progress_listener_reentrant() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    // A listener using the progress (adding a listener) while notified
+    let progress = Progress::new();
+    let handle = progress.clone();
+    let added: Arc<Mutex<u32>> = Arc::default();
+    let count = Arc::clone(&added);
+    progress.on_update(move |u| {
+        if u.finished {
+            let count = Arc::clone(&count);
+            handle.on_update(move |_| *count.lock().unwrap() += 1);
+        }
+    });
+
+    let mut ctx = ProgressContext {
+        progress: progress.clone(),
+    };
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    let migrations: Vec<BoxMigration<ProgressContext>> = vec![
+        Box::new(items_migration(1, 1)),
+        Box::new(items_migration(2, 1)),
+    ];
+
+    Migratex::new(&mut ctx, &mut meta, migrations)
+        .with_progress(progress)
+        .migrate_to_latest()
+        .await?;
+
+    // Added after step 1: notified of the updates of step 2
+    assert!(*added.lock().unwrap() > 0);
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the redo and reset operations.
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use std::collections::BTreeSet;
+
+use migratex::{MetaStatus, Metadata, Migratex, MigratexError};
+use okerr::Result;
+
+use common::{TempDir, TestContext, TestMetadata, create_test_migrations};
+
+#[tokio::test]
+async fn test_redo() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(3));
+    mx.migrate_to_latest().await?;
+    mx.redo().await?;
+    drop(mx);
+
+    assert_eq!(ctx.events()[3..], ["down 3", "up 3"]);
+    assert_eq!(meta.version(), 3);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+    assert_eq!(meta.applied, BTreeSet::from([1, 2, 3]));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_redo_n() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(3));
+    mx.migrate_to_latest().await?;
+    mx.redo_n(2).await?;
+
+    // Capped at version 0
+    mx.redo_n(10).await?;
+    drop(mx);
+
+    assert_eq!(
+        ctx.events()[3..],
+        [
+            "down 3", "down 2", "up 2", "up 3", "down 3", "down 2", "down 1", "up 1", "up 2",
+            "up 3"
+        ]
+    );
+    assert_eq!(meta.version(), 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_redo_nothing_applied() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(3));
+    mx.redo().await?;
+    drop(mx);
+
+    assert!(ctx.events().is_empty());
+    assert_eq!(meta.version(), 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reset() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(3));
+    mx.migrate_to(2).await?;
+    mx.reset().await?;
+    drop(mx);
+
+    assert_eq!(
+        ctx.events()[2..],
+        ["down 2", "down 1", "up 1", "up 2", "up 3"]
+    );
+    assert_eq!(meta.version(), 3);
+    assert_eq!(meta.applied, BTreeSet::from([1, 2, 3]));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_redo_reset_refused_on_production() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx =
+        Migratex::new(&mut ctx, &mut meta, create_test_migrations(3)).with_production(true);
+    mx.migrate_to_latest().await?;
+
+    for err in [mx.redo().await.unwrap_err(), mx.reset().await.unwrap_err()] {
+        assert!(matches!(
+            err.downcast_ref::<MigratexError>(),
+            Some(MigratexError::Production { .. })
+        ));
+    }
+    drop(mx);
+
+    assert_eq!(ctx.events(), ["up 1", "up 2", "up 3"]);
+
+    Ok(())
+}
@@ -0,0 +1,237 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the repeatable migrations (re-run when their checksum changes).
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use async_trait::async_trait;
+use migratex::{
+    BoxMigration, BoxRepeatable, FileBackup, MetaStatus, Metadata, Migratex, Migration,
+    RepeatableMigration, checksum,
+};
+use okerr::{Result, fail};
+
+use common::{TempDir, TestMetadata};
+
+/// Context recording the runs (versions and repeatable migration names).
+#[derive(Debug, Default)]
+struct ViewsContext {
+    runs: Vec<String>,
+    fail_view: bool,
+    fail_view_named: Option<&'static str>,
+}
+
+/// Versioned migration recording its steps.
+struct CreateTable(i32);
+
+#[async_trait]
+impl Migration<ViewsContext> for CreateTable {
+    fn version(&self) -> i32 {
+        self.0
+    }
+
+    async fn up(&self, ctx: &mut ViewsContext) -> Result<()> {
+        ctx.runs.push(format!("up {}", self.0));
+        Ok(())
+    }
+
+    async fn down(&self, ctx: &mut ViewsContext) -> Result<()> {
+        ctx.runs.push(format!("down {}", self.0));
+        Ok(())
+    }
+}
+
+/// Repeatable migration (re)creating a view from its SQL definition.
+struct View {
+    name: &'static str,
+    sql: &'static str,
+}
+
+#[async_trait]
+impl RepeatableMigration<ViewsContext> for View {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn checksum(&self) -> String {
+        checksum(self.sql)
+    }
+
+    async fn run(&self, ctx: &mut ViewsContext) -> Result<()> {
+        if ctx.fail_view || ctx.fail_view_named == Some(self.name) {
+            fail!("cannot create the view {}", self.name);
+        }
+        ctx.runs.push(self.name.to_string());
+        Ok(())
+    }
+}
+
+fn migrations() -> Vec<BoxMigration<ViewsContext>> {
+    vec![Box::new(CreateTable(1)), Box::new(CreateTable(2))]
+}
+
+fn views(active_users: &'static str) -> Vec<BoxRepeatable<ViewsContext>> {
+    vec![
+        Box::new(View {
+            name: "active_users",
+            sql: active_users,
+        }),
+        Box::new(View {
+            name: "totals",
+            sql: "SELECT COUNT(*) FROM users",
+        }),
+    ]
+}
+
+#[test]
+fn test_repeatable_checksum() {
+    assert_eq!(checksum(""), "cbf29ce484222325");
+    assert_eq!(checksum("SELECT 1"), checksum("SELECT 1"));
+    assert_ne!(checksum("SELECT 1"), checksum("SELECT 2"));
+}
+
+#[tokio::test]
+async fn test_repeatable_run_after_versioned() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+    let mut ctx = ViewsContext::default();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations())
+        .with_repeatable_migrations(views("SELECT * FROM users WHERE active"));
+
+    assert_eq!(mx.plan(2)?.repeatable, vec!["active_users", "totals"]);
+    // Not up to the latest version
+    assert!(mx.plan(1)?.repeatable.is_empty());
+
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(ctx.runs, vec!["up 1", "up 2", "active_users", "totals"]);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+    assert_eq!(
+        meta.checksums.get("active_users"),
+        Some(&checksum("SELECT * FROM users WHERE active"))
+    );
+
+    // Recorded in the store
+    meta.save(&path)?;
+    assert_eq!(TestMetadata::load_or_init(&path)?.checksums, meta.checksums);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_repeatable_rerun_on_change() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = ViewsContext::default();
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations())
+        .with_repeatable_migrations(views("SELECT * FROM users WHERE active"));
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    // Unchanged: nothing to run
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations())
+        .with_repeatable_migrations(views("SELECT * FROM users WHERE active"));
+    assert!(mx.plan(2)?.is_empty());
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(ctx.runs.len(), 4);
+
+    // Changed definition: only the changed migration runs again
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations())
+        .with_repeatable_migrations(views("SELECT * FROM users WHERE active = 1"));
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(ctx.runs[4..], ["active_users"]);
+    assert_eq!(
+        meta.checksums.get("active_users"),
+        Some(&checksum("SELECT * FROM users WHERE active = 1"))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_repeatable_failure() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = ViewsContext {
+        fail_view: true,
+        ..Default::default()
+    };
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations())
+        .with_repeatable_migrations(views("SELECT * FROM users WHERE active"));
+
+    let err = mx.migrate_to_latest().await.unwrap_err();
+    assert_eq!(err.to_string(), "repeatable migration active_users");
+    drop(mx);
+
+    // The versioned migrations are applied, the checksum is not stored
+    assert_eq!(meta.version(), 2);
+    assert_eq!(meta.status(), MetaStatus::Failed);
+    assert!(meta.checksums.is_empty());
+
+    // Retried on the next run
+    ctx.fail_view = false;
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations())
+        .with_repeatable_migrations(views("SELECT * FROM users WHERE active"));
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(ctx.runs[2..], ["active_users", "totals"]);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_repeatable_checksums_restored() -> Result<()> {
+    let temp = TempDir::new()?;
+    let data = temp.path().join("data");
+    std::fs::create_dir_all(&data)?;
+
+    let mut ctx = ViewsContext {
+        fail_view_named: Some("totals"),
+        ..Default::default()
+    };
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations())
+        .with_repeatable_migrations(views("SELECT * FROM users WHERE active"))
+        .with_backup(FileBackup::new(temp.path().join("backups")).with_path(&data))
+        .with_restore_on_failure(true);
+
+    assert!(mx.migrate_to_latest().await.is_err());
+    drop(mx);
+
+    // active_users ran, but the snapshot is restored: its checksum is put back too
+    assert_eq!(ctx.runs, vec!["up 1", "up 2", "active_users"]);
+    assert_eq!(meta.version(), 0);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+    assert!(meta.checksums.is_empty());
+
+    // Run again against the restored data
+    ctx.fail_view_named = None;
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations())
+        .with_repeatable_migrations(views("SELECT * FROM users WHERE active"));
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(ctx.runs[3..], ["up 1", "up 2", "active_users", "totals"]);
+    assert_eq!(meta.checksums.len(), 2);
+
+    Ok(())
+}
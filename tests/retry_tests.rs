@@ -0,0 +1,211 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the retry policy.
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use migratex::{
+    Backoff, BoxMigration, MetaStatus, Metadata, Migratex, Migration, MigrationObserver, PlanStep,
+    RetryPolicy,
+};
+use okerr::{Error, Result, fail};
+
+use common::{TempDir, TestMetadata};
+
+/// Context counting the attempts.
+#[derive(Default)]
+struct RetryContext {
+    attempts: u32,
+}
+
+/// Migration failing (with a "database is locked" error) before succeeding.
+struct FlakyMigration {
+    failures: u32,
+    retryable: bool,
+}
+
+#[async_trait]
+impl Migration<RetryContext> for FlakyMigration {
+    fn version(&self) -> i32 {
+        1
+    }
+
+    fn retryable(&self) -> bool {
+        self.retryable
+    }
+
+    async fn up(&self, ctx: &mut RetryContext) -> Result<()> {
+        ctx.attempts += 1;
+        if ctx.attempts <= self.failures {
+            fail!("database is locked");
+        }
+        Ok(())
+    }
+
+    async fn down(&self, _ctx: &mut RetryContext) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Observer recording the retried attempts.
+#[derive(Default)]
+struct RetryObserver {
+    retries: Mutex<Vec<u32>>,
+}
+
+impl MigrationObserver for RetryObserver {
+    fn on_retry(&self, _step: &PlanStep, _error: &Error, attempt: u32) {
+        self.retries.lock().unwrap().push(attempt);
+    }
+}
+
+fn policy(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy::new(max_attempts).with_backoff(Backoff::Fixed(Duration::from_millis(1)))
+}
+
+async fn run(
+    migration: FlakyMigration,
+    policy: RetryPolicy,
+    observer: Arc<RetryObserver>,
+) -> Result<(Result<()>, RetryContext, TestMetadata)> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = RetryContext::default();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    let migrations: Vec<BoxMigration<RetryContext>> = vec![Box::new(migration)];
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations)
+        .with_retry(policy)
+        .with_observer(observer);
+    let result = mx.migrate_to_latest().await;
+    drop(mx);
+
+    Ok((result, ctx, meta))
+}
+
+#[tokio::test]
+async fn test_retry_transient_failures() -> Result<()> {
+    let observer = Arc::new(RetryObserver::default());
+    let migration = FlakyMigration {
+        failures: 2,
+        retryable: true,
+    };
+
+    let (result, ctx, meta) = run(migration, policy(3), observer.clone()).await?;
+
+    assert!(result.is_ok());
+    assert_eq!(ctx.attempts, 3);
+    assert_eq!(*observer.retries.lock().unwrap(), vec![1, 2]);
+    assert_eq!(meta.version(), 1);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_retry_max_attempts() -> Result<()> {
+    let observer = Arc::new(RetryObserver::default());
+    let migration = FlakyMigration {
+        failures: 5,
+        retryable: true,
+    };
+
+    let (result, ctx, meta) = run(migration, policy(3), observer.clone()).await?;
+
+    assert_eq!(result.unwrap_err().to_string(), "database is locked");
+    assert_eq!(ctx.attempts, 3);
+    assert_eq!(*observer.retries.lock().unwrap(), vec![1, 2]);
+    assert_eq!(meta.version(), 0);
+    assert_eq!(meta.status(), MetaStatus::Failed);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_retry_requires_opt_in() -> Result<()> {
+    let observer = Arc::new(RetryObserver::default());
+    let migration = FlakyMigration {
+        failures: 1,
+        retryable: false,
+    };
+
+    let (result, ctx, _) = run(migration, policy(3), observer.clone()).await?;
+
+    assert!(result.is_err());
+    assert_eq!(ctx.attempts, 1);
+    assert!(observer.retries.lock().unwrap().is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_retry_transient_predicate() -> Result<()> {
+    let observer = Arc::new(RetryObserver::default());
+    let migration = FlakyMigration {
+        failures: 1,
+        retryable: true,
+    };
+    let policy = policy(3).with_transient(|e| e.to_string().contains("disk I/O error"));
+
+    let (result, ctx, _) = run(migration, policy, observer.clone()).await?;
+
+    assert!(result.is_err());
+    assert_eq!(ctx.attempts, 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_retry_not_past_run_deadline() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = RetryContext::default();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    let migrations: Vec<BoxMigration<RetryContext>> = vec![Box::new(FlakyMigration {
+        failures: 1,
+        retryable: true,
+    })];
+
+    // A backoff too far to be represented is past any run deadline
+    let policy = RetryPolicy::new(3).with_backoff(Backoff::Fixed(Duration::MAX));
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations)
+        .with_retry(policy)
+        .with_timeout(Duration::from_secs(3600));
+    let result = mx.migrate_to_latest().await;
+    drop(mx);
+
+    assert_eq!(result.unwrap_err().to_string(), "database is locked");
+    assert_eq!(ctx.attempts, 1);
+
+    Ok(())
+}
+
+#[test]
+fn test_backoff_delay() {
+    let fixed = Backoff::Fixed(Duration::from_millis(50));
+    assert_eq!(fixed.delay(1), Duration::from_millis(50));
+    assert_eq!(fixed.delay(4), Duration::from_millis(50));
+
+    let exponential = Backoff::Exponential {
+        initial: Duration::from_millis(100),
+        max: Duration::from_millis(500),
+    };
+    assert_eq!(exponential.delay(1), Duration::from_millis(100));
+    assert_eq!(exponential.delay(2), Duration::from_millis(200));
+    assert_eq!(exponential.delay(3), Duration::from_millis(400));
+    assert_eq!(exponential.delay(4), Duration::from_millis(500));
+    assert_eq!(exponential.delay(40), Duration::from_millis(500));
+}
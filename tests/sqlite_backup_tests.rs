@@ -0,0 +1,287 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the SQLite online backup (VACUUM INTO).
+
+#![cfg(feature = "sqlx")]
+
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use migratex::{
+    Backup, BoxMigration, MetaStatus, Metadata, Migratex, Migration, SqliteMetadata, SqliteStorage,
+    connect_to_sqlite,
+};
+use okerr::{Result, fail};
+use sqlx::SqlitePool;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+
+use common::TempDir;
+
+/// Context holding the database pool.
+struct DbContext {
+    pool: Arc<SqlitePool>,
+}
+
+/// Migration creating the products table.
+struct CreateProducts;
+
+#[async_trait]
+impl Migration<DbContext> for CreateProducts {
+    fn version(&self) -> i32 {
+        1
+    }
+
+    async fn up(&self, ctx: &mut DbContext) -> Result<()> {
+        sqlx::query("CREATE TABLE products (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT)")
+            .execute(&*ctx.pool)
+            .await?;
+        sqlx::query("CREATE INDEX products_name ON products (name)")
+            .execute(&*ctx.pool)
+            .await?;
+        sqlx::query("INSERT INTO products (name) VALUES ('apple'), ('pear')")
+            .execute(&*ctx.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, ctx: &mut DbContext) -> Result<()> {
+        sqlx::query("DROP TABLE products")
+            .execute(&*ctx.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Migration corrupting the products, then failing.
+struct CorruptProducts;
+
+#[async_trait]
+impl Migration<DbContext> for CorruptProducts {
+    fn version(&self) -> i32 {
+        2
+    }
+
+    async fn up(&self, ctx: &mut DbContext) -> Result<()> {
+        sqlx::query("DELETE FROM products WHERE name = 'apple'")
+            .execute(&*ctx.pool)
+            .await?;
+        fail!("bad migration");
+    }
+
+    async fn down(&self, _ctx: &mut DbContext) -> Result<()> {
+        Ok(())
+    }
+}
+
+async fn storage(temp: &TempDir) -> Result<SqliteStorage> {
+    let pool = connect_to_sqlite(temp.path().join("app.db")).await?;
+    Ok(SqliteStorage::new(Arc::new(pool)))
+}
+
+async fn products(pool: &SqlitePool) -> Result<Vec<String>> {
+    let rows: Vec<(String,)> = sqlx::query_as("SELECT name FROM products ORDER BY id")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.into_iter().map(|(name,)| name).collect())
+}
+
+#[tokio::test]
+async fn test_sqlite_backup_restore_on_failure() -> Result<()> {
+    let temp = TempDir::new()?;
+    let storage = storage(&temp).await?;
+    let backup = storage.backup(temp.path().join("backups"));
+
+    let mut ctx = DbContext {
+        pool: storage.pool.clone(),
+    };
+    let mut meta: SqliteMetadata = SqliteMetadata::load_or_init(&storage).await?;
+
+    let migrations: Vec<BoxMigration<DbContext>> = vec![Box::new(CreateProducts)];
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations);
+    mx.migrate_to_latest().await?;
+    drop(mx);
+    meta.save(&storage).await?;
+
+    let migrations: Vec<BoxMigration<DbContext>> =
+        vec![Box::new(CreateProducts), Box::new(CorruptProducts)];
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations)
+        .with_backup(backup.clone())
+        .with_restore_on_failure(true);
+
+    assert!(mx.migrate_to_latest().await.is_err());
+    drop(mx);
+
+    assert_eq!(products(&storage.pool).await?, vec!["apple", "pear"]);
+    assert_eq!(meta.version(), 1);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+    assert_eq!(backup.snapshots().await?.len(), 1);
+
+    // The restored schema is complete (index, AUTOINCREMENT counter)
+    let (indexes,): (i64,) = sqlx::query_as(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'index' AND name = 'products_name'",
+    )
+    .fetch_one(&*storage.pool)
+    .await?;
+    assert_eq!(indexes, 1);
+
+    sqlx::query("INSERT INTO products (name) VALUES ('plum')")
+        .execute(&*storage.pool)
+        .await?;
+    let (id,): (i64,) = sqlx::query_as("SELECT id FROM products WHERE name = 'plum'")
+        .fetch_one(&*storage.pool)
+        .await?;
+    assert_eq!(id, 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_backup_restore_on_demand() -> Result<()> {
+    let temp = TempDir::new()?;
+    let storage = storage(&temp).await?;
+    let backup = storage.backup(temp.path().join("backups"));
+
+    let mut meta: SqliteMetadata = SqliteMetadata::load_or_init(&storage).await?;
+    let snapshot = backup.snapshot().await?;
+    assert!(snapshot.path.is_file());
+
+    let mut ctx = DbContext {
+        pool: storage.pool.clone(),
+    };
+    let migrations: Vec<BoxMigration<DbContext>> = vec![Box::new(CreateProducts)];
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations);
+    mx.migrate_to_latest().await?;
+    drop(mx);
+    meta.save(&storage).await?;
+
+    backup.restore(&snapshot).await?;
+
+    let (tables,): (i64,) =
+        sqlx::query_as("SELECT COUNT(*) FROM sqlite_master WHERE name = 'products'")
+            .fetch_one(&*storage.pool)
+            .await?;
+    assert_eq!(tables, 0);
+
+    let loaded: SqliteMetadata = SqliteMetadata::load_or_init(&storage).await?;
+    assert_eq!(loaded.version(), 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_backup_retention() -> Result<()> {
+    let temp = TempDir::new()?;
+    let storage = storage(&temp).await?;
+    let backup = storage.backup(temp.path().join("backups")).with_keep(2);
+
+    let first = backup.snapshot().await?;
+    let second = backup.snapshot().await?;
+    let third = backup.snapshot().await?;
+
+    assert_eq!(backup.snapshots().await?, vec![second, third.clone()]);
+    assert_eq!(backup.latest().await?, Some(third));
+    assert!(!first.path.exists());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_backup_restore_special_tables() -> Result<()> {
+    let temp = TempDir::new()?;
+    let storage = storage(&temp).await?;
+    let backup = storage.backup(temp.path().join("backups"));
+
+    // Generated column, full-text search (virtual and shadow tables),
+    // and a name matching `sqlite_%` when `_` is a wildcard
+    for sql in [
+        "CREATE TABLE notes (id INTEGER PRIMARY KEY, body TEXT,
+             size INTEGER GENERATED ALWAYS AS (length(body)) STORED)",
+        "CREATE VIRTUAL TABLE notes_search USING fts5(body)",
+        "CREATE TABLE sqlitexnotes (body TEXT)",
+        "INSERT INTO notes (body) VALUES ('apple pie'), ('pear')",
+        "INSERT INTO notes_search (body) VALUES ('apple pie'), ('pear')",
+        "INSERT INTO sqlitexnotes (body) VALUES ('kept')",
+    ] {
+        sqlx::query(sql).execute(&*storage.pool).await?;
+    }
+
+    let snapshot = backup.snapshot().await?;
+
+    for sql in [
+        "DELETE FROM notes",
+        "DELETE FROM notes_search",
+        "DROP TABLE sqlitexnotes",
+    ] {
+        sqlx::query(sql).execute(&*storage.pool).await?;
+    }
+
+    backup.restore(&snapshot).await?;
+
+    let notes: Vec<(String, i64)> = sqlx::query_as("SELECT body, size FROM notes ORDER BY id")
+        .fetch_all(&*storage.pool)
+        .await?;
+    assert_eq!(notes, vec![("apple pie".into(), 9), ("pear".into(), 4)]);
+
+    let found: Vec<(String,)> =
+        sqlx::query_as("SELECT body FROM notes_search WHERE notes_search MATCH 'apple'")
+            .fetch_all(&*storage.pool)
+            .await?;
+    assert_eq!(found, vec![("apple pie".into(),)]);
+
+    let (kept,): (String,) = sqlx::query_as("SELECT body FROM sqlitexnotes")
+        .fetch_one(&*storage.pool)
+        .await?;
+    assert_eq!(kept, "kept");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_backup_interrupted_restore() -> Result<()> {
+    let temp = TempDir::new()?;
+
+    // A single connection, reused by each restore
+    let options = SqliteConnectOptions::new()
+        .filename(temp.path().join("app.db"))
+        .create_if_missing(true)
+        .pragma("foreign_keys", "ON");
+    let pool = SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await?;
+    let storage = SqliteStorage::new(Arc::new(pool));
+    let backup = storage.backup(temp.path().join("backups"));
+
+    sqlx::query("CREATE TABLE notes (body TEXT)")
+        .execute(&*storage.pool)
+        .await?;
+    let snapshot = backup.snapshot().await?;
+
+    // Dropped at any point: the connection is not put back attached, or without foreign keys
+    for micros in [0, 100, 500, 1_000, 5_000] {
+        let _ =
+            tokio::time::timeout(Duration::from_micros(micros), backup.restore(&snapshot)).await;
+
+        let (foreign_keys,): (i64,) = sqlx::query_as("PRAGMA foreign_keys")
+            .fetch_one(&*storage.pool)
+            .await?;
+        let (attached,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM pragma_database_list WHERE name = 'migratex_backup'",
+        )
+        .fetch_one(&*storage.pool)
+        .await?;
+        assert_eq!((foreign_keys, attached), (1, 0));
+    }
+
+    backup.restore(&snapshot).await?;
+
+    Ok(())
+}
@@ -0,0 +1,220 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the SQLite schema snapshot and diff.
+
+#![cfg(feature = "sqlx")]
+
+mod common;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use migratex::{
+    BoxMigration, Migratex, Migration, SqliteMetadata, SqliteSchema, SqliteStorage,
+    connect_to_sqlite,
+};
+use okerr::Result;
+use sqlx::SqlitePool;
+
+use common::TempDir;
+
+/// Context holding the database pool.
+struct DbContext {
+    pool: Arc<SqlitePool>,
+}
+
+/// Migration running SQL statements.
+struct Sql {
+    version: i32,
+    up: &'static [&'static str],
+}
+
+#[async_trait]
+impl Migration<DbContext> for Sql {
+    fn version(&self) -> i32 {
+        self.version
+    }
+
+    async fn up(&self, ctx: &mut DbContext) -> Result<()> {
+        for sql in self.up {
+            sqlx::query(sql).execute(&*ctx.pool).await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, _ctx: &mut DbContext) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The migrations of a release: 1 creates the users, 2 adds their email.
+fn migrations() -> Vec<BoxMigration<DbContext>> {
+    vec![
+        Box::new(Sql {
+            version: 1,
+            up: &[
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT NOT NULL)",
+                "CREATE TABLE posts (
+                    id INTEGER PRIMARY KEY,
+                    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+                    title TEXT UNIQUE
+                )",
+                "CREATE INDEX posts_user ON posts (user_id)",
+            ],
+        }),
+        Box::new(Sql {
+            version: 2,
+            up: &[
+                "ALTER TABLE users ADD COLUMN email TEXT DEFAULT ''",
+                "CREATE VIEW named_users AS SELECT id, name FROM users WHERE name <> ''",
+            ],
+        }),
+    ]
+}
+
+/// Migrate a new database (in the temp dir) with the migrations.
+async fn migrate(
+    temp: &TempDir,
+    file: &str,
+    migrations: Vec<BoxMigration<DbContext>>,
+) -> Result<SqliteStorage> {
+    let pool = connect_to_sqlite(temp.path().join(file)).await?;
+    let storage = SqliteStorage::new(Arc::new(pool));
+    let mut ctx = DbContext {
+        pool: storage.pool.clone(),
+    };
+    let mut meta: SqliteMetadata = SqliteMetadata::load_or_init(&storage).await?;
+
+    Migratex::new(&mut ctx, &mut meta, migrations)
+        .migrate_to_latest()
+        .await?;
+    meta.save(&storage).await?;
+
+    Ok(storage)
+}
+
+#[tokio::test]
+async fn test_sqlite_schema_dump() -> Result<()> {
+    let temp = TempDir::new()?;
+    let storage = migrate(&temp, "app.db", migrations()).await?;
+
+    let schema = storage.schema().await?;
+    let text = schema.as_str();
+
+    assert!(text.starts_with("pragma application_id = 0\npragma user_version = 0\n"));
+    assert!(text.contains("\ntable users\n  column id INTEGER primary key 1\n"));
+    assert!(text.contains("  column email TEXT default ''\n"));
+    assert!(text.contains(
+        "  foreign key (user_id) references users (id) on update NO ACTION on delete CASCADE\n"
+    ));
+    assert!(text.contains("  index posts_user (user_id)\n  index u (title) unique\n"));
+    assert!(text.contains(
+        "\nview named_users\n  CREATE VIEW named_users AS SELECT id, name FROM users WHERE name <> ''\n"
+    ));
+
+    // Without the metadata tables
+    assert!(!text.contains("_migratex_metadata"));
+    assert!(
+        SqliteSchema::dump(&storage.pool)
+            .await?
+            .as_str()
+            .contains("table _migratex_metadata_applied")
+    );
+
+    // Only the internal tables are hidden (`_` is not a wildcard)
+    sqlx::query("CREATE TABLE sqlitexnotes (body TEXT)")
+        .execute(&*storage.pool)
+        .await?;
+    let text = storage.schema().await?.to_string();
+    assert!(text.contains("\ntable sqlitexnotes\n"));
+    assert!(!text.contains("sqlite_"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_schema_migrated_forward_matches_fresh() -> Result<()> {
+    let temp = TempDir::new()?;
+
+    // Fresh install of the latest release
+    let fresh = migrate(&temp, "fresh.db", migrations()).await?;
+
+    // Older database (release 1), then migrated forward
+    let mut first_release = migrations();
+    first_release.truncate(1);
+    let old = migrate(&temp, "old.db", first_release).await?;
+    let old = migrate_again(&old).await?;
+
+    fresh.schema().await?.check_same(&old.schema().await?)?;
+
+    // A diverging database
+    sqlx::query("CREATE INDEX users_email ON users (email)")
+        .execute(&*old.pool)
+        .await?;
+
+    let diff = fresh
+        .schema()
+        .await?
+        .diff(&old.schema().await?)
+        .expect("schemas differ");
+    assert_eq!(diff, "  table users\n+   index users_email (email)\n");
+
+    Ok(())
+}
+
+/// Migrate an existing database to the latest release.
+async fn migrate_again(storage: &SqliteStorage) -> Result<SqliteStorage> {
+    let mut ctx = DbContext {
+        pool: storage.pool.clone(),
+    };
+    let mut meta: SqliteMetadata = SqliteMetadata::load_or_init(storage).await?;
+
+    Migratex::new(&mut ctx, &mut meta, migrations())
+        .migrate_to_latest()
+        .await?;
+    meta.save(storage).await?;
+
+    Ok(storage.clone())
+}
+
+#[tokio::test]
+async fn test_sqlite_schema_snapshot() -> Result<()> {
+    let temp = TempDir::new()?;
+    let storage = migrate(&temp, "app.db", migrations()).await?;
+    let snapshot = temp.path().join("snapshots").join("schema.txt");
+
+    // Missing: failing (not written)
+    let schema = storage.schema().await?;
+    let err = schema.check_snapshot(&snapshot).unwrap_err();
+    assert!(err.to_string().contains("is missing"));
+    assert!(err.to_string().contains("MIGRATEX_UPDATE_SNAPSHOTS=1"));
+    assert!(!snapshot.exists());
+
+    // Written, then compared
+    schema.save(&snapshot)?;
+    assert_eq!(SqliteSchema::load(&snapshot)?, schema);
+    schema.check_snapshot(&snapshot)?;
+
+    sqlx::query("DROP VIEW named_users")
+        .execute(&*storage.pool)
+        .await?;
+
+    let err = storage
+        .schema()
+        .await?
+        .check_snapshot(&snapshot)
+        .unwrap_err();
+    let message = err.to_string();
+
+    assert!(message.contains("MIGRATEX_UPDATE_SNAPSHOTS=1"));
+    assert!(message.ends_with(
+        "- view named_users\n-   CREATE VIEW named_users AS SELECT id, name FROM users WHERE name <> ''\n"
+    ));
+
+    Ok(())
+}
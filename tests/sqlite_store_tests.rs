@@ -0,0 +1,242 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for SqliteMetadata functionality.
+
+#![cfg(feature = "sqlx")]
+
+mod common;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+use migratex::{MetaStatus, Metadata, SqliteMetadata, SqliteStorage, connect_to_sqlite};
+use okerr::Result;
+
+use common::TempDir;
+
+/// Connect to a new SQLite database in the temp dir.
+async fn storage(temp: &TempDir) -> Result<SqliteStorage> {
+    let pool = connect_to_sqlite(temp.path().join("app.db")).await?;
+    Ok(SqliteStorage::new(Arc::new(pool)))
+}
+
+#[tokio::test]
+async fn test_sqlite_store_init_and_reload() -> Result<()> {
+    let temp = TempDir::new()?;
+    let storage = storage(&temp).await?;
+
+    let mut meta: SqliteMetadata = SqliteMetadata::load_or_init(&storage).await?;
+    assert_eq!(meta.version(), 0);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+
+    meta.set_version(3);
+    meta.applied = BTreeSet::from([1, 2, 3]);
+    meta.skipped = BTreeSet::from([2]);
+    meta.set_checksum("users_view".to_string(), "cbf29ce484222325".to_string());
+    meta.mark_failed();
+    meta.save(&storage).await?;
+
+    let loaded: SqliteMetadata = SqliteMetadata::load_or_init(&storage).await?;
+    assert_eq!(loaded.version(), 3);
+    assert_eq!(loaded.status(), MetaStatus::Failed);
+    assert_eq!(loaded.applied, BTreeSet::from([1, 2, 3]));
+    assert_eq!(loaded.skipped, BTreeSet::from([2]));
+    assert_eq!(
+        loaded.checksums,
+        BTreeMap::from([("users_view".to_string(), "cbf29ce484222325".to_string())])
+    );
+    assert_eq!(loaded.created_at(), meta.created_at());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_store_i64_versions() -> Result<()> {
+    let temp = TempDir::new()?;
+    let storage = storage(&temp).await?;
+
+    let mut meta: SqliteMetadata<i64> = SqliteMetadata::load_or_init(&storage).await?;
+    meta.set_version(20261017123045);
+    meta.applied = BTreeSet::from([20261017123045]);
+    meta.save(&storage).await?;
+
+    let loaded: SqliteMetadata<i64> = SqliteMetadata::load_or_init(&storage).await?;
+    assert_eq!(loaded.version(), 20261017123045);
+    assert_eq!(loaded.applied, BTreeSet::from([20261017123045]));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_store_string_versions() -> Result<()> {
+    let temp = TempDir::new()?;
+    let storage = storage(&temp).await?;
+
+    let mut meta: SqliteMetadata<String> = SqliteMetadata::load_or_init(&storage).await?;
+    meta.set_version("002.003.000".to_string());
+    meta.applied = BTreeSet::from(["002.003.000".to_string()]);
+    meta.save(&storage).await?;
+
+    let loaded: SqliteMetadata<String> = SqliteMetadata::load_or_init(&storage).await?;
+    assert_eq!(loaded.version(), "002.003.000");
+    assert_eq!(loaded.applied.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_store_string_versions_kept_as_text() -> Result<()> {
+    let temp = TempDir::new()?;
+    let storage = storage(&temp).await?;
+    let track = storage.clone().with_track("db");
+
+    // Numeric-looking versions, changed by an integer conversion ("1.001", "7")
+    for (storage, version) in [(&storage, "0001.0010"), (&track, "007")] {
+        let mut meta: SqliteMetadata<String> = SqliteMetadata::load_or_init(storage).await?;
+        meta.set_version(version.to_string());
+        meta.applied = BTreeSet::from([version.to_string()]);
+        meta.save(storage).await?;
+
+        let loaded: SqliteMetadata<String> = SqliteMetadata::load_or_init(storage).await?;
+        assert_eq!(loaded.version(), version);
+        assert_eq!(loaded.applied, BTreeSet::from([version.to_string()]));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_store_legacy_integer_version() -> Result<()> {
+    let temp = TempDir::new()?;
+    let storage = storage(&temp).await?;
+
+    // Metadata table of a previous release, with an INTEGER version
+    sqlx::query(
+        "CREATE TABLE _migratex_metadata (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            version INTEGER NOT NULL DEFAULT 0,
+            status TEXT NOT NULL DEFAULT 'Clean',
+            app_version TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+    )
+    .execute(&*storage.pool)
+    .await?;
+    sqlx::query(
+        "INSERT INTO _migratex_metadata (id, version, status, app_version, created_at, updated_at)
+         VALUES (1, 7, 'Clean', '0.2.2', '2026-01-01', '2026-01-01')",
+    )
+    .execute(&*storage.pool)
+    .await?;
+
+    // Converted to TEXT when loaded
+    let meta: SqliteMetadata = SqliteMetadata::load_or_init(&storage).await?;
+    assert_eq!(meta.version(), 7);
+    assert_eq!(meta.created_at(), "2026-01-01");
+
+    let (kind,): (String,) = sqlx::query_as(
+        "SELECT type FROM pragma_table_info('_migratex_metadata') WHERE name = 'version'",
+    )
+    .fetch_one(&*storage.pool)
+    .await?;
+    assert_eq!(kind, "TEXT");
+
+    // Another version type can then be stored as is
+    let mut meta: SqliteMetadata<String> = SqliteMetadata::load_or_init(&storage).await?;
+    meta.set_version("0001.0010".to_string());
+    meta.save(&storage).await?;
+
+    let loaded: SqliteMetadata<String> = SqliteMetadata::load_or_init(&storage).await?;
+    assert_eq!(loaded.version(), "0001.0010");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_store_legacy_app_version() -> Result<()> {
+    let temp = TempDir::new()?;
+    let storage = storage(&temp).await?;
+
+    // Metadata table of a previous release, with the version of migratex as app version
+    sqlx::query(
+        "CREATE TABLE _migratex_metadata (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            version INTEGER NOT NULL DEFAULT 0,
+            status TEXT NOT NULL DEFAULT 'Clean',
+            app_version TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )",
+    )
+    .execute(&*storage.pool)
+    .await?;
+    sqlx::query(
+        "INSERT INTO _migratex_metadata (id, version, status, app_version, created_at, updated_at)
+         VALUES (1, 2, 'Clean', '0.2.2', '2026-01-01', '2026-01-01')",
+    )
+    .execute(&*storage.pool)
+    .await?;
+
+    let mut meta: SqliteMetadata = SqliteMetadata::load_or_init(&storage).await?;
+    assert_eq!(meta.version(), 2);
+    assert_eq!(meta.app_version(), "0.2.2");
+    assert!(!meta.app_version_recorded());
+
+    meta.set_app_version("0.1.0".to_string());
+    meta.save(&storage).await?;
+
+    let loaded: SqliteMetadata = SqliteMetadata::load_or_init(&storage).await?;
+    assert_eq!(loaded.app_version(), "0.1.0");
+    assert!(loaded.app_version_recorded());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_sqlite_store_tracks() -> Result<()> {
+    let temp = TempDir::new()?;
+    let storage = storage(&temp).await?;
+    let db = storage.clone().with_track("db");
+    let files = storage.clone().with_track("files");
+
+    let mut meta: SqliteMetadata = SqliteMetadata::load_or_init(&storage).await?;
+    meta.set_version(1);
+    meta.save(&storage).await?;
+
+    let mut meta: SqliteMetadata = SqliteMetadata::load_or_init(&db).await?;
+    assert_eq!(meta.version(), 0);
+    meta.set_version(4);
+    meta.applied = BTreeSet::from([2, 4]);
+    meta.set_checksum("users_view".to_string(), "1".to_string());
+    meta.save(&db).await?;
+
+    let mut meta: SqliteMetadata = SqliteMetadata::load_or_init(&files).await?;
+    meta.set_version(2);
+    meta.applied = BTreeSet::from([1, 2]);
+    meta.mark_failed();
+    meta.save(&files).await?;
+
+    let loaded: SqliteMetadata = SqliteMetadata::load_or_init(&storage).await?;
+    assert_eq!(loaded.version(), 1);
+    assert!(loaded.applied.is_empty());
+
+    let loaded: SqliteMetadata = SqliteMetadata::load_or_init(&db).await?;
+    assert_eq!(loaded.version(), 4);
+    assert_eq!(loaded.status(), MetaStatus::Clean);
+    assert_eq!(loaded.applied, BTreeSet::from([2, 4]));
+    assert_eq!(loaded.checksums.len(), 1);
+
+    let loaded: SqliteMetadata = SqliteMetadata::load_or_init(&files).await?;
+    assert_eq!(loaded.version(), 2);
+    assert_eq!(loaded.status(), MetaStatus::Failed);
+    assert_eq!(loaded.applied, BTreeSet::from([1, 2]));
+    assert!(loaded.checksums.is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,203 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for baseline and squashed migrations.
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use migratex::{BoxMigration, MetaStatus, Metadata, Migratex, MigratexError};
+use okerr::Result;
+
+use common::{TempDir, TestContext, TestMetadata, TestMigration, create_test_migrations};
+
+/// A squash of the versions 1..=80, followed by the migrations 81 and 82.
+fn squashed_migrations() -> Vec<BoxMigration<TestContext>> {
+    vec![
+        Box::new(TestMigration::new(80, "squash_1_80").squashing(0)),
+        Box::new(TestMigration::new(81, "m81")),
+        Box::new(TestMigration::new(82, "m82")),
+    ]
+}
+
+#[tokio::test]
+async fn test_baseline_does_not_run_migrations() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(5));
+    mx.baseline(3);
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 5);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+    assert_eq!(ctx.applied_migrations, vec![4, 5]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rollback_stops_at_baseline() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    let migrations: Vec<BoxMigration<TestContext>> = vec![
+        Box::new(TestMigration::new(11, "m11")),
+        Box::new(TestMigration::new(12, "m12")),
+    ];
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations);
+    mx.baseline(10);
+    mx.migrate_to_latest().await?;
+    mx.migrate_prev().await?;
+    mx.migrate_prev().await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 10);
+    assert!(ctx.applied_migrations.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_squash_runs_on_fresh_install() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, squashed_migrations());
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 82);
+    assert_eq!(ctx.applied_migrations, vec![80, 81, 82]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_squash_is_skipped_above_its_range() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    meta.set_version(80);
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, squashed_migrations());
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 82);
+    assert_eq!(ctx.applied_migrations, vec![81, 82]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_squash_refused_inside_its_range() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    meta.set_version(42);
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, squashed_migrations());
+    let err = mx.migrate_to_latest().await.unwrap_err();
+    drop(mx);
+
+    assert!(matches!(
+        err.downcast_ref::<MigratexError>(),
+        Some(MigratexError::InsideSquash {
+            version: 80,
+            at: 42,
+            ..
+        })
+    ));
+    assert_eq!(meta.version(), 42);
+    assert!(ctx.applied_migrations.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_squash_cannot_target_inside_its_range() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    let mx = Migratex::new(&mut ctx, &mut meta, squashed_migrations());
+
+    assert!(mx.plan(10).is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_squash_down_goes_to_its_start() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, squashed_migrations());
+    mx.migrate_to(80).await?;
+    mx.migrate_prev().await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 0);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+    assert!(ctx.applied_migrations.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_squash_down_from_legacy_applied() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    // Existing install, migrated with the old migrations 1..=80
+    meta.set_version(80);
+    if let Some(applied) = meta.applied_versions_mut() {
+        applied.extend(1..=80);
+    }
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, squashed_migrations());
+    mx.migrate_to(0).await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 0);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+    assert!(meta.applied_versions().is_some_and(|a| a.is_empty()));
+
+    // Not stuck inside the squash
+    let mut mx = Migratex::new(&mut ctx, &mut meta, squashed_migrations());
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 82);
+    assert_eq!(ctx.applied_migrations, vec![80, 81, 82]);
+
+    Ok(())
+}
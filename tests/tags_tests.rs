@@ -0,0 +1,117 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the tag-based conditional migrations.
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use std::collections::BTreeSet;
+
+use migratex::{BoxMigration, Metadata, Migratex};
+use okerr::Result;
+
+use common::{TempDir, TestContext, TestMetadata, TestMigration};
+
+/// 1: untagged, 2: dev seed data (irreversible), 3: prod backfill, 4: untagged.
+fn migrations() -> Vec<BoxMigration<TestContext>> {
+    vec![
+        Box::new(TestMigration::new(1, "untagged")),
+        Box::new(
+            TestMigration::new(2, "dev_seed")
+                .with_tags(&["dev"])
+                .irreversible(),
+        ),
+        Box::new(TestMigration::new(3, "backfill").with_tags(&["prod", "staging"])),
+        Box::new(TestMigration::new(4, "untagged")),
+    ]
+}
+
+#[tokio::test]
+async fn test_tags_no_filter_runs_all() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations());
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(ctx.applied_migrations, vec![1, 2, 3, 4]);
+    assert!(meta.skipped.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tags_filter_skips_steps() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations()).with_tags(["prod"]);
+
+    let plan = mx.plan(4)?;
+    let skipped: Vec<_> = plan.steps.iter().map(|s| s.skipped).collect();
+    assert_eq!(skipped, vec![false, true, false, false]);
+
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(ctx.applied_migrations, vec![1, 3, 4]);
+    assert_eq!(meta.version(), 4);
+    assert_eq!(meta.applied, BTreeSet::from([1, 2, 3, 4]));
+    assert_eq!(meta.skipped, BTreeSet::from([2]));
+
+    // Recorded in the store
+    meta.save(&path)?;
+    assert_eq!(
+        TestMetadata::load_or_init(&path)?.skipped,
+        BTreeSet::from([2])
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tags_rollback_skipped_steps() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations()).with_tags(["prod"]);
+    mx.migrate_to_latest().await?;
+
+    // The skipped (irreversible) migration 2 does not block the rollback, and is not run
+    mx.migrate_to_zero().await?;
+    drop(mx);
+
+    assert!(ctx.applied_migrations.is_empty());
+    assert_eq!(meta.version(), 0);
+    assert!(meta.applied.is_empty());
+    assert!(meta.skipped.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tags_any_accepted_tag() -> Result<()> {
+    let temp = TempDir::new()?;
+    let mut ctx = TestContext::new();
+    let mut meta = TestMetadata::load_or_init(temp.metadata_path())?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations()).with_tags(["staging", "linux"]);
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(ctx.applied_migrations, vec![1, 3, 4]);
+    assert_eq!(meta.skipped, BTreeSet::from([2]));
+
+    Ok(())
+}
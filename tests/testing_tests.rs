@@ -0,0 +1,121 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the migrations test harness (up / down round trips).
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use async_trait::async_trait;
+use migratex::testing::RoundTrip;
+use migratex::{BoxMigration, Migration};
+use okerr::Result;
+
+/// Context holding a key-value state.
+#[derive(Debug, Default)]
+struct KvContext {
+    state: BTreeMap<String, i32>,
+}
+
+/// Migration setting a key (and removing it on down, unless `leaky`).
+struct SetKey {
+    version: i32,
+    leaky: bool,
+    reversible: bool,
+}
+
+#[async_trait]
+impl Migration<KvContext> for SetKey {
+    fn version(&self) -> i32 {
+        self.version
+    }
+
+    fn name(&self) -> &str {
+        "set_key"
+    }
+
+    fn reversible(&self) -> bool {
+        self.reversible
+    }
+
+    async fn up(&self, ctx: &mut KvContext) -> Result<()> {
+        ctx.state
+            .insert(format!("key{}", self.version), self.version);
+        Ok(())
+    }
+
+    async fn down(&self, ctx: &mut KvContext) -> Result<()> {
+        if !self.leaky {
+            ctx.state.remove(&format!("key{}", self.version));
+        }
+        Ok(())
+    }
+}
+
+fn set_key(version: i32, leaky: bool, reversible: bool) -> BoxMigration<KvContext> {
+    Box::new(SetKey {
+        version,
+        leaky,
+        reversible,
+    })
+}
+
+async fn run(migrations: Vec<BoxMigration<KvContext>>) -> Result<()> {
+    RoundTrip::new(migrations)
+        .run(
+            async || Ok(KvContext::default()),
+            async |ctx: &KvContext| Ok(ctx.state.clone()),
+        )
+        .await
+}
+
+#[tokio::test]
+async fn test_round_trip_ok() -> Result<()> {
+    run(vec![
+        set_key(2, false, true),
+        set_key(1, false, true),
+        // Only applied
+        set_key(3, true, false),
+    ])
+    .await
+}
+
+#[tokio::test]
+async fn test_round_trip_down_not_reverting() -> Result<()> {
+    let err = run(vec![set_key(1, false, true), set_key(2, true, true)])
+        .await
+        .unwrap_err();
+
+    let message = err.to_string();
+    assert!(message.starts_with("round trip of migration 2 (set_key): the down does not revert"));
+    assert!(message.contains("after down: {\"key1\": 1, \"key2\": 2}"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_round_trip_new_context_per_migration() -> Result<()> {
+    let contexts = AtomicUsize::new(0);
+
+    RoundTrip::new(vec![
+        set_key(1, false, true),
+        set_key(2, false, true),
+        set_key(3, false, true),
+    ])
+    .run(
+        async || {
+            contexts.fetch_add(1, Ordering::SeqCst);
+            Ok(KvContext::default())
+        },
+        async |ctx: &KvContext| Ok(ctx.state.clone()),
+    )
+    .await?;
+
+    assert_eq!(contexts.load(Ordering::SeqCst), 3);
+
+    Ok(())
+}
@@ -0,0 +1,283 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the timeouts and the cancellation.
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Wake, Waker};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use migratex::{
+    BoxMigration, CancellationToken, MetaStatus, Metadata, Migratex, MigratexError, Migration,
+};
+use okerr::Result;
+
+use common::{TempDir, TestMetadata};
+
+/// Context recording the applied migrations, with a cancellation token.
+#[derive(Default)]
+struct SlowContext {
+    applied: Vec<i32>,
+    token: CancellationToken,
+}
+
+/// Migration taking some time, optionally cancelling the run.
+struct SlowMigration {
+    version: i32,
+    delay: Duration,
+    timeout: Option<Duration>,
+    cancel: bool,
+}
+
+impl SlowMigration {
+    fn new(version: i32, delay_ms: u64) -> Self {
+        Self {
+            version,
+            delay: Duration::from_millis(delay_ms),
+            timeout: None,
+            cancel: false,
+        }
+    }
+
+    fn with_timeout(mut self, timeout_ms: u64) -> Self {
+        self.timeout = Some(Duration::from_millis(timeout_ms));
+        self
+    }
+
+    fn cancelling(mut self) -> Self {
+        self.cancel = true;
+        self
+    }
+}
+
+#[async_trait]
+impl Migration<SlowContext> for SlowMigration {
+    fn version(&self) -> i32 {
+        self.version
+    }
+
+    fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    async fn up(&self, ctx: &mut SlowContext) -> Result<()> {
+        tokio::time::sleep(self.delay).await;
+        ctx.applied.push(self.version);
+        if self.cancel {
+            ctx.token.cancel();
+        }
+        Ok(())
+    }
+
+    async fn down(&self, ctx: &mut SlowContext) -> Result<()> {
+        ctx.applied.retain(|v| *v != self.version);
+        Ok(())
+    }
+}
+
+fn error_of(result: Result<()>) -> MigratexError {
+    result
+        .unwrap_err()
+        .downcast::<MigratexError>()
+        .expect("a MigratexError")
+}
+
+#[tokio::test]
+async fn test_migration_timeout() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = SlowContext::default();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    let migrations: Vec<BoxMigration<SlowContext>> = vec![
+        Box::new(SlowMigration::new(1, 0)),
+        Box::new(SlowMigration::new(2, 5_000).with_timeout(50)),
+        Box::new(SlowMigration::new(3, 0)),
+    ];
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations);
+    let err = error_of(mx.migrate_to_latest().await);
+    drop(mx);
+
+    assert!(matches!(
+        err,
+        MigratexError::Timeout { version: 2, timeout, .. } if timeout == Duration::from_millis(50)
+    ));
+    assert_eq!(ctx.applied, vec![1]);
+    assert_eq!(meta.version(), 1);
+    assert_eq!(meta.status(), MetaStatus::Failed);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_default_step_timeout() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = SlowContext::default();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    let migrations: Vec<BoxMigration<SlowContext>> = vec![
+        // Its own timeout overrides the default one
+        Box::new(SlowMigration::new(1, 100).with_timeout(5_000)),
+        Box::new(SlowMigration::new(2, 5_000)),
+    ];
+
+    let mut mx =
+        Migratex::new(&mut ctx, &mut meta, migrations).with_step_timeout(Duration::from_millis(50));
+    let err = error_of(mx.migrate_to_latest().await);
+    drop(mx);
+
+    assert!(matches!(err, MigratexError::Timeout { version: 2, .. }));
+    assert_eq!(ctx.applied, vec![1]);
+    assert_eq!(meta.version(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_run_timeout() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    // The running step is bounded by the remaining time of the run
+    let mut ctx = SlowContext::default();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    let migrations: Vec<BoxMigration<SlowContext>> = vec![Box::new(SlowMigration::new(1, 5_000))];
+
+    let mut mx =
+        Migratex::new(&mut ctx, &mut meta, migrations).with_timeout(Duration::from_millis(50));
+    let err = error_of(mx.migrate_to_latest().await);
+    drop(mx);
+
+    assert!(matches!(err, MigratexError::Timeout { version: 1, .. }));
+    assert_eq!(meta.status(), MetaStatus::Failed);
+
+    // Elapsed before a step: stopped between two steps
+    let mut ctx = SlowContext::default();
+    let mut meta = TestMetadata::load_or_init(temp.path().join("other.json"))?;
+    let migrations: Vec<BoxMigration<SlowContext>> = vec![Box::new(SlowMigration::new(1, 0))];
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations).with_timeout(Duration::ZERO);
+    let err = error_of(mx.migrate_to_latest().await);
+    drop(mx);
+
+    assert!(matches!(err, MigratexError::RunTimeout { version: 0, .. }));
+    assert!(ctx.applied.is_empty());
+    assert_eq!(meta.version(), 0);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_unbounded_timeouts() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    // Too far to be represented: no deadline
+    let mut ctx = SlowContext::default();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    let migrations: Vec<BoxMigration<SlowContext>> = vec![
+        Box::new(SlowMigration::new(1, 10)),
+        Box::new(SlowMigration::new(2, 0)),
+    ];
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations)
+        .with_timeout(Duration::MAX)
+        .with_step_timeout(Duration::MAX);
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(ctx.applied, vec![1, 2]);
+    assert_eq!(meta.version(), 2);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cancellation_between_steps() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = SlowContext::default();
+    let token = ctx.token.clone();
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    let migrations: Vec<BoxMigration<SlowContext>> = vec![
+        Box::new(SlowMigration::new(1, 0)),
+        // Cancelled while running: the step completes, the next ones are not run
+        Box::new(SlowMigration::new(2, 0).cancelling()),
+        Box::new(SlowMigration::new(3, 0)),
+    ];
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations).with_cancellation(token.clone());
+    let err = error_of(mx.migrate_to_latest().await);
+    drop(mx);
+
+    assert!(matches!(err, MigratexError::Cancelled { version: 2 }));
+    assert!(token.is_cancelled());
+    assert_eq!(ctx.applied, vec![1, 2]);
+    assert_eq!(meta.version(), 2);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_cancellation_token_wait() {
+    let token = CancellationToken::new();
+    let waiter = token.clone();
+
+    let handle = tokio::spawn(async move {
+        waiter.cancelled().await;
+        waiter.is_cancelled()
+    });
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    token.cancel();
+
+    assert!(handle.await.unwrap());
+}
+
+/// Waker doing nothing, its clones are counted by the `Arc`.
+struct NoopWaker;
+
+impl Wake for NoopWaker {
+    fn wake(self: Arc<Self>) {}
+}
+
+#[test]
+fn test_cancellation_token_releases_wakers() {
+    let token = CancellationToken::new();
+    let counter = Arc::new(NoopWaker);
+    let waker = Waker::from(counter.clone());
+    let mut cx = Context::from_waker(&waker);
+
+    // Checked alongside each item: a new future per item, dropped pending
+    for _ in 0..100 {
+        let mut cancelled = pin!(token.cancelled());
+        assert!(cancelled.as_mut().poll(&mut cx).is_pending());
+        assert!(cancelled.as_mut().poll(&mut cx).is_pending());
+    }
+
+    // Only `counter` and `waker` remain
+    assert_eq!(Arc::strong_count(&counter), 2);
+
+    let mut cancelled = pin!(token.cancelled());
+    assert!(cancelled.as_mut().poll(&mut cx).is_pending());
+    token.cancel();
+    assert_eq!(Arc::strong_count(&counter), 2);
+    assert!(cancelled.as_mut().poll(&mut cx).is_ready());
+}
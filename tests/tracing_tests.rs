@@ -0,0 +1,131 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the tracing integration.
+
+#![cfg(all(feature = "json", feature = "tracing"))]
+
+mod common;
+
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+use migratex::Migratex;
+use okerr::Result;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+use common::{TempDir, TestContext, TestMetadata, create_test_migrations};
+
+/// Collect the fields of a span or an event as `name=value` strings.
+struct FieldsVisitor<'a>(&'a mut Vec<String>);
+
+impl Visit for FieldsVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        self.0.push(format!("{}={:?}", field.name(), value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.push(format!("{}={}", field.name(), value));
+    }
+}
+
+/// Subscriber recording the spans and events.
+#[derive(Clone, Default)]
+struct RecordingSubscriber {
+    lines: Arc<Mutex<Vec<String>>>,
+    next_id: Arc<Mutex<u64>>,
+}
+
+impl RecordingSubscriber {
+    fn lines(&self) -> Vec<String> {
+        self.lines.lock().unwrap().clone()
+    }
+
+    fn push(&self, kind: &str, name: &str, fields: Vec<String>) {
+        self.lines
+            .lock()
+            .unwrap()
+            .push(format!("{} {} {}", kind, name, fields.join(" ")));
+    }
+}
+
+impl Subscriber for RecordingSubscriber {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut fields = Vec::new();
+        span.record(&mut FieldsVisitor(&mut fields));
+        self.push("span", span.metadata().name(), fields);
+
+        let mut next_id = self.next_id.lock().unwrap();
+        *next_id += 1;
+        Id::from_u64(*next_id)
+    }
+
+    fn record(&self, _span: &Id, values: &Record<'_>) {
+        let mut fields = Vec::new();
+        values.record(&mut FieldsVisitor(&mut fields));
+        self.push("record", "", fields);
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Vec::new();
+        event.record(&mut FieldsVisitor(&mut fields));
+        self.push("event", event.metadata().level().as_str(), fields);
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[tokio::test]
+async fn test_tracing_spans_and_events() -> Result<()> {
+    let subscriber = RecordingSubscriber::default();
+    let _guard = tracing::subscriber::set_default(subscriber.clone());
+
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = TestContext::with_fail_at(2);
+    let mut meta = TestMetadata::load_or_init(&path)?;
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, create_test_migrations(2));
+    assert!(mx.migrate_to_latest().await.is_err());
+    drop(mx);
+
+    meta.save(&path)?;
+    TestMetadata::load_or_init(&path)?;
+
+    let lines = subscriber.lines();
+    let has = |needle: &str| lines.iter().any(|l| l.contains(needle));
+
+    assert!(has("span migrate_to from=0 target=2 direction=Up steps=2"));
+    assert!(has(
+        "span migration_step version=1 name=Migration_1 direction=Up"
+    ));
+    assert!(has("record  outcome=success"));
+    assert!(has(
+        "span migration_step version=2 name=Migration_2 direction=Up"
+    ));
+    assert!(has("message=migratex migration step failed"));
+    assert!(has("record  outcome=failure"));
+    assert!(has("message=migratex metadata marked failed"));
+    assert!(has("message=migratex metadata saved"));
+    assert!(has("message=migratex metadata loaded"));
+    assert!(has(
+        "message=migratex metadata loaded version=1 status=Failed"
+    ));
+
+    Ok(())
+}
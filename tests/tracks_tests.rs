@@ -0,0 +1,344 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the multi-track orchestrator (and the dependencies between tracks).
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use migratex::{BoxMigration, Dependency, Metadata, Migratex, MigratexError, TrackStep, Tracks};
+use okerr::Result;
+
+use common::{EventLog, TempDir, TestContext, TestMetadata, TestMigration, create_test_migrations};
+
+#[tokio::test]
+async fn test_tracks_migrate_in_order() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut db_ctx = TestContext::new();
+    let mut files_ctx = TestContext::new();
+    let mut db_meta = TestMetadata::load_or_init_track(&path, "db")?;
+    let mut files_meta = TestMetadata::load_or_init_track(&path, "files")?;
+
+    let db = Migratex::new(&mut db_ctx, &mut db_meta, create_test_migrations(3));
+    let files = Migratex::new(&mut files_ctx, &mut files_meta, create_test_migrations(2));
+
+    let mut tracks = Tracks::new()
+        .with_track("db", db)
+        .with_track("files", files);
+    assert_eq!(tracks.names(), vec!["db", "files"]);
+
+    let plans = tracks.plan_latest()?;
+    assert_eq!(plans[0].0, "db");
+    assert_eq!(plans[0].1.steps.len(), 3);
+    assert_eq!(plans[1].0, "files");
+    assert_eq!(plans[1].1.steps.len(), 2);
+
+    tracks.migrate_to_latest().await?;
+    assert_eq!(tracks.track("db").unwrap().version(), 3);
+    drop(tracks);
+
+    db_meta.save(&path)?;
+    files_meta.save(&path)?;
+
+    assert_eq!(db_ctx.applied_migrations, vec![1, 2, 3]);
+    assert_eq!(files_ctx.applied_migrations, vec![1, 2]);
+    assert_eq!(TestMetadata::load_or_init_track(&path, "db")?.version(), 3);
+    assert_eq!(
+        TestMetadata::load_or_init_track(&path, "files")?.version(),
+        2
+    );
+    assert_eq!(TestMetadata::load_or_init(&path)?.version(), 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tracks_stop_at_failing_track() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut db_ctx = TestContext::with_fail_at(2);
+    let mut files_ctx = TestContext::new();
+    let mut db_meta = TestMetadata::load_or_init_track(&path, "db")?;
+    let mut files_meta = TestMetadata::load_or_init_track(&path, "files")?;
+
+    let db = Migratex::new(&mut db_ctx, &mut db_meta, create_test_migrations(3));
+    let files = Migratex::new(&mut files_ctx, &mut files_meta, create_test_migrations(2));
+
+    let mut tracks = Tracks::new()
+        .with_track("db", db)
+        .with_track("files", files);
+
+    let err = tracks.migrate_to_latest().await.unwrap_err();
+    assert_eq!(err.to_string(), "track db");
+    assert_eq!(
+        format!("{:#}", err),
+        "track db: Intentional failure at version 2"
+    );
+    drop(tracks);
+
+    assert_eq!(db_meta.version(), 1);
+    assert!(files_ctx.applied_migrations.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tracks_rollback_in_reverse_order() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut db_ctx = TestContext::new();
+    let mut files_ctx = TestContext::new();
+    let mut db_meta = TestMetadata::load_or_init_track(&path, "db")?;
+    let mut files_meta = TestMetadata::load_or_init_track(&path, "files")?;
+
+    let db = Migratex::new(&mut db_ctx, &mut db_meta, create_test_migrations(2));
+    let files = Migratex::new(&mut files_ctx, &mut files_meta, create_test_migrations(2));
+
+    let mut tracks = Tracks::new()
+        .with_track("db", db)
+        .with_track("files", files);
+    tracks.migrate_to_latest().await?;
+    drop(tracks);
+
+    // The "files" track fails to roll back: "db" (rolled back after it) is left untouched
+    files_ctx.should_fail_at_version = Some(1);
+
+    let db = Migratex::new(&mut db_ctx, &mut db_meta, create_test_migrations(2));
+    let files = Migratex::new(&mut files_ctx, &mut files_meta, create_test_migrations(2));
+
+    let mut tracks = Tracks::new()
+        .with_track("db", db)
+        .with_track("files", files);
+    assert!(tracks.migrate_to_zero().await.is_err());
+    drop(tracks);
+
+    assert_eq!(files_meta.version(), 1);
+    assert_eq!(db_meta.version(), 2);
+    assert_eq!(db_ctx.applied_migrations, vec![1, 2]);
+
+    Ok(())
+}
+
+/// Migrations `1..=count`, with the dependencies of some versions.
+fn dep_migrations(count: i32, deps: &[(i32, Dependency)]) -> Vec<BoxMigration<TestContext>> {
+    (1..=count)
+        .map(|version| {
+            let migration = deps
+                .iter()
+                .filter(|(v, _)| *v == version)
+                .fold(TestMigration::new(version, ""), |m, (_, d)| {
+                    m.depending_on(d.clone())
+                });
+            Box::new(migration) as BoxMigration<TestContext>
+        })
+        .collect()
+}
+
+fn scheduled(schedule: &[TrackStep]) -> Vec<String> {
+    schedule
+        .iter()
+        .map(|s| format!("{}/{}", s.track, s.step.version))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_tracks_dependencies_order() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+    let log = EventLog::default();
+
+    let mut plugin_ctx = TestContext::in_track("plugin", &log);
+    let mut core_ctx = TestContext::in_track("core", &log);
+    let mut plugin_meta = TestMetadata::load_or_init_track(&path, "plugin")?;
+    let mut core_meta = TestMetadata::load_or_init_track(&path, "core")?;
+
+    let plugin = Migratex::new(
+        &mut plugin_ctx,
+        &mut plugin_meta,
+        dep_migrations(2, &[(1, Dependency::new("core", 2))]),
+    );
+    let core = Migratex::new(&mut core_ctx, &mut core_meta, dep_migrations(3, &[]));
+
+    // The plugin track is preferred, but waits for the core version 2
+    let mut tracks = Tracks::new()
+        .with_track("plugin", plugin)
+        .with_track("core", core);
+
+    assert_eq!(
+        scheduled(&tracks.schedule_latest()?),
+        vec!["core/1", "core/2", "plugin/1", "plugin/2", "core/3"]
+    );
+
+    tracks.migrate_to_latest().await?;
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "up core/1",
+            "up core/2",
+            "up plugin/1",
+            "up plugin/2",
+            "up core/3"
+        ]
+    );
+    log.lock().unwrap().clear();
+
+    // Rollback: the plugin migration is rolled back before the core leaves the version 2
+    assert_eq!(
+        scheduled(&tracks.schedule_zero()?),
+        vec!["core/3", "plugin/2", "plugin/1", "core/2", "core/1"]
+    );
+
+    tracks.migrate_to_zero().await?;
+    drop(tracks);
+
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec![
+            "down core/3",
+            "down plugin/2",
+            "down plugin/1",
+            "down core/2",
+            "down core/1"
+        ]
+    );
+    assert_eq!(plugin_meta.version(), 0);
+    assert_eq!(core_meta.version(), 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tracks_dependency_already_reached() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+    let log = EventLog::default();
+
+    let mut plugin_ctx = TestContext::in_track("plugin", &log);
+    let mut core_ctx = TestContext::in_track("core", &log);
+    let mut plugin_meta = TestMetadata::load_or_init_track(&path, "plugin")?;
+    let mut core_meta = TestMetadata::load_or_init_track(&path, "core")?;
+    core_meta.set_version(2);
+
+    let plugin = Migratex::new(
+        &mut plugin_ctx,
+        &mut plugin_meta,
+        dep_migrations(1, &[(1, Dependency::new("core", 2))]),
+    );
+    let core = Migratex::new(&mut core_ctx, &mut core_meta, dep_migrations(3, &[]));
+
+    let tracks = Tracks::new()
+        .with_track("plugin", plugin)
+        .with_track("core", core);
+
+    assert_eq!(
+        scheduled(&tracks.schedule_latest()?),
+        vec!["plugin/1", "core/3"]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tracks_dependency_cycle() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+    let log = EventLog::default();
+
+    let mut a_ctx = TestContext::in_track("a", &log);
+    let mut b_ctx = TestContext::in_track("b", &log);
+    let mut a_meta = TestMetadata::load_or_init_track(&path, "a")?;
+    let mut b_meta = TestMetadata::load_or_init_track(&path, "b")?;
+
+    let a = Migratex::new(
+        &mut a_ctx,
+        &mut a_meta,
+        dep_migrations(2, &[(2, Dependency::new("b", 1))]),
+    );
+    let b = Migratex::new(
+        &mut b_ctx,
+        &mut b_meta,
+        dep_migrations(1, &[(1, Dependency::new("a", 2))]),
+    );
+
+    let mut tracks = Tracks::new().with_track("a", a).with_track("b", b);
+
+    let err = tracks.migrate_to_latest().await.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<MigratexError>(),
+        Some(MigratexError::DependencyCycle { steps }) if *steps == vec!["a/2", "b/1"]
+    ));
+
+    // Nothing was run
+    assert!(log.lock().unwrap().is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tracks_unsatisfied_dependency() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+    let log = EventLog::default();
+
+    let mut plugin_ctx = TestContext::in_track("plugin", &log);
+    let mut core_ctx = TestContext::in_track("core", &log);
+    let mut plugin_meta = TestMetadata::load_or_init_track(&path, "plugin")?;
+    let mut core_meta = TestMetadata::load_or_init_track(&path, "core")?;
+
+    let plugin = Migratex::new(
+        &mut plugin_ctx,
+        &mut plugin_meta,
+        dep_migrations(1, &[(1, Dependency::new("core", 5))]),
+    );
+    let core = Migratex::new(&mut core_ctx, &mut core_meta, dep_migrations(3, &[]));
+
+    let tracks = Tracks::new()
+        .with_track("plugin", plugin)
+        .with_track("core", core);
+
+    let err = tracks.schedule_latest().unwrap_err();
+    assert!(
+        err.to_string().ends_with(
+            "depends on core 5, but the track core is at 0 and its latest migration is 3"
+        )
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_tracks_unknown_dependency_track() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+    let log = EventLog::default();
+
+    let mut plugin_ctx = TestContext::in_track("plugin", &log);
+    let mut plugin_meta = TestMetadata::load_or_init_track(&path, "plugin")?;
+
+    let plugin = Migratex::new(
+        &mut plugin_ctx,
+        &mut plugin_meta,
+        dep_migrations(1, &[(1, Dependency::new("core", 1))]),
+    );
+
+    let tracks = Tracks::new().with_track("plugin", plugin);
+
+    let err = tracks.schedule_latest().unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<MigratexError>(),
+        Some(MigratexError::UnsatisfiedDependency { track, version: 1, .. }) if track == "plugin"
+    ));
+    assert!(err.to_string().ends_with("the track core is unknown"));
+
+    Ok(())
+}
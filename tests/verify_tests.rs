@@ -0,0 +1,189 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the pre- and post-conditions of the migrations.
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use migratex::{
+    Backoff, BoxMigration, Direction, MetaStatus, Metadata, Migratex, MigratexError, Migration,
+    RetryPolicy,
+};
+use okerr::{Result, fail};
+
+use common::{TempDir, TestMetadata};
+
+/// Context holding the "rows" of the data.
+#[derive(Default)]
+struct RowsContext {
+    rows: u32,
+    ups: u32,
+}
+
+/// Migration adding rows, checking and verifying the row count.
+struct RowsMigration {
+    /// Rows added by `up` (and removed by `down`).
+    added: u32,
+    /// Rows expected after `up`.
+    expected: u32,
+    /// Maximum rows accepted before `up`.
+    max_before: u32,
+    reversible: bool,
+}
+
+#[async_trait]
+impl Migration<RowsContext> for RowsMigration {
+    fn version(&self) -> i32 {
+        1
+    }
+
+    fn reversible(&self) -> bool {
+        self.reversible
+    }
+
+    fn retryable(&self) -> bool {
+        true
+    }
+
+    async fn up(&self, ctx: &mut RowsContext) -> Result<()> {
+        ctx.ups += 1;
+        ctx.rows += self.added;
+        Ok(())
+    }
+
+    async fn down(&self, ctx: &mut RowsContext) -> Result<()> {
+        ctx.rows -= self.added;
+        Ok(())
+    }
+
+    async fn check_before(&self, ctx: &mut RowsContext, direction: Direction) -> Result<()> {
+        if direction == Direction::Up && ctx.rows > self.max_before {
+            fail!("{} rows before the migration", ctx.rows);
+        }
+        Ok(())
+    }
+
+    async fn verify_after(&self, ctx: &mut RowsContext, direction: Direction) -> Result<()> {
+        if direction == Direction::Up && ctx.rows != self.expected {
+            fail!("expected {} rows, found {}", self.expected, ctx.rows);
+        }
+        Ok(())
+    }
+}
+
+fn migration(expected: u32) -> RowsMigration {
+    RowsMigration {
+        added: 10,
+        expected,
+        max_before: 0,
+        reversible: true,
+    }
+}
+
+async fn run(
+    migration: RowsMigration,
+    mut ctx: RowsContext,
+) -> Result<(Result<()>, RowsContext, TestMetadata)> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut meta = TestMetadata::load_or_init(&path)?;
+    let migrations: Vec<BoxMigration<RowsContext>> = vec![Box::new(migration)];
+
+    let policy = RetryPolicy::new(3).with_backoff(Backoff::Fixed(Duration::from_millis(1)));
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations).with_retry(policy);
+    let result = mx.migrate_to_latest().await;
+    drop(mx);
+
+    Ok((result, ctx, meta))
+}
+
+#[tokio::test]
+async fn test_verify_passes() -> Result<()> {
+    let (result, ctx, meta) = run(migration(10), RowsContext::default()).await?;
+
+    assert!(result.is_ok());
+    assert_eq!(ctx.rows, 10);
+    assert_eq!(meta.version(), 1);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_check_before_fails() -> Result<()> {
+    let ctx = RowsContext {
+        rows: 5,
+        ..Default::default()
+    };
+
+    let (result, ctx, meta) = run(migration(15), ctx).await?;
+
+    let err = result.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<MigratexError>(),
+        Some(MigratexError::CheckFailed { version: 1, .. })
+    ));
+    assert_eq!(ctx.ups, 0);
+    assert_eq!(ctx.rows, 5);
+    assert_eq!(meta.version(), 0);
+    assert_eq!(meta.status(), MetaStatus::Failed);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_verify_after_fails_rolls_back() -> Result<()> {
+    let (result, ctx, meta) = run(migration(20), RowsContext::default()).await?;
+
+    let err = result.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<MigratexError>(),
+        Some(MigratexError::VerifyFailed {
+            version: 1,
+            rolled_back: true,
+            ..
+        })
+    ));
+    assert!(err.to_string().ends_with("expected 20 rows, found 10"));
+    // Not retried
+    assert_eq!(ctx.ups, 1);
+    assert_eq!(ctx.rows, 0);
+    assert_eq!(meta.version(), 0);
+    assert_eq!(meta.status(), MetaStatus::Failed);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_verify_after_fails_irreversible() -> Result<()> {
+    let migration = RowsMigration {
+        reversible: false,
+        ..migration(20)
+    };
+
+    let (result, ctx, meta) = run(migration, RowsContext::default()).await?;
+
+    let err = result.unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<MigratexError>(),
+        Some(MigratexError::VerifyFailed {
+            rolled_back: false,
+            ..
+        })
+    ));
+    assert_eq!(ctx.rows, 10);
+    assert_eq!(meta.version(), 0);
+    assert_eq!(meta.status(), MetaStatus::Failed);
+
+    Ok(())
+}
@@ -0,0 +1,185 @@
+// This file is part of "Migratex - A Migrations Toolkit".
+//
+// This source code is licensed under the MIT license, please view the LICENSE
+// file distributed with this source code. For the full
+// information and documentation: https://github.com/nicolab/migratex
+// -----------------------------------------------------------------------------
+
+// -- Tests for the non-i32 version types.
+
+#![cfg(feature = "json")]
+
+mod common;
+
+use migratex::{
+    BoxMigration, FnMigration, JsonMetadata, MetaStatus, Metadata, Migratex, MigratexError, Version,
+};
+use okerr::Result;
+
+use common::TempDir;
+
+/// Migration context recording the applied versions (encoded).
+#[derive(Debug, Default)]
+struct VersionContext {
+    applied: Vec<String>,
+}
+
+/// Create a migration recording its version in the context.
+fn recording_migration<V: Version>(version: V) -> BoxMigration<VersionContext, V> {
+    let up_version = version.to_storage();
+    let down_version = version.to_storage();
+
+    Box::new(FnMigration::new(
+        version,
+        "recording",
+        move |ctx: &mut VersionContext| {
+            let v = up_version.clone();
+            Box::pin(async move {
+                ctx.applied.push(v);
+                Ok(())
+            })
+        },
+        move |ctx: &mut VersionContext| {
+            let v = down_version.clone();
+            Box::pin(async move {
+                ctx.applied.retain(|a| *a != v);
+                Ok(())
+            })
+        },
+    ))
+}
+
+#[test]
+fn test_version_storage_round_trip() -> Result<()> {
+    assert_eq!(i32::from_storage(&42.to_storage())?, 42);
+    assert_eq!(
+        i64::from_storage(&20261017123045_i64.to_storage())?,
+        20261017123045
+    );
+    assert_eq!(String::from_storage("abc")?, "abc");
+    assert!(i32::from_storage("not a number").is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_i64_timestamp_versions() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = VersionContext::default();
+    let mut meta: JsonMetadata<i64> = JsonMetadata::load_or_init(&path)?;
+    let migrations = vec![
+        recording_migration(20261017123045_i64),
+        recording_migration(20261018090000_i64),
+    ];
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations);
+    assert_eq!(mx.latest_version(), 20261018090000);
+
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), 20261018090000);
+    assert_eq!(meta.status(), MetaStatus::Clean);
+    assert_eq!(ctx.applied, vec!["20261017123045", "20261018090000"]);
+
+    // Persisted
+    meta.save(&path)?;
+    let loaded: JsonMetadata<i64> = JsonMetadata::load_or_init(&path)?;
+    assert_eq!(loaded.version(), 20261018090000);
+    assert_eq!(loaded.applied.len(), 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_i64_out_of_order_error() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = VersionContext::default();
+    let mut meta: JsonMetadata<i64> = JsonMetadata::load_or_init(&path)?;
+
+    let mut mx = Migratex::new(
+        &mut ctx,
+        &mut meta,
+        vec![recording_migration(20261018090000_i64)],
+    );
+    mx.migrate_to_latest().await?;
+    drop(mx);
+
+    let mut mx = Migratex::new(
+        &mut ctx,
+        &mut meta,
+        vec![
+            recording_migration(20261017123045_i64),
+            recording_migration(20261018090000_i64),
+        ],
+    );
+    let err = mx.migrate_to_latest().await.unwrap_err();
+
+    assert!(matches!(
+        err.downcast_ref::<MigratexError<i64>>(),
+        Some(MigratexError::OutOfOrder { versions, .. }) if versions == &vec![20261017123045]
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_string_versions() -> Result<()> {
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = VersionContext::default();
+    let mut meta: JsonMetadata<String> = JsonMetadata::load_or_init(&path)?;
+    let migrations = vec![
+        recording_migration("2026-01-a".to_string()),
+        recording_migration("2026-02-b".to_string()),
+        recording_migration("2026-03-c".to_string()),
+    ];
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations);
+
+    mx.migrate_to_latest().await?;
+    mx.migrate_prev().await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), "2026-02-b");
+    assert_eq!(ctx.applied, vec!["2026-01-a", "2026-02-b"]);
+
+    Ok(())
+}
+
+#[cfg(feature = "semver")]
+#[tokio::test]
+async fn test_semver_versions() -> Result<()> {
+    use semver::Version as SemVer;
+
+    let temp = TempDir::new()?;
+    let path = temp.metadata_path();
+
+    let mut ctx = VersionContext::default();
+    let mut meta: JsonMetadata<SemVer> = JsonMetadata::load_or_init(&path)?;
+    let migrations = vec![
+        recording_migration(SemVer::parse("2.3.0")?),
+        recording_migration(SemVer::parse("2.10.0")?),
+        recording_migration(SemVer::parse("10.0.0")?),
+    ];
+
+    let mut mx = Migratex::new(&mut ctx, &mut meta, migrations);
+
+    mx.migrate_to(SemVer::parse("2.10.0")?).await?;
+    drop(mx);
+
+    assert_eq!(meta.version(), SemVer::parse("2.10.0")?);
+    assert_eq!(ctx.applied, vec!["2.3.0", "2.10.0"]);
+
+    // Persisted as a string
+    meta.save(&path)?;
+    let loaded: JsonMetadata<SemVer> = JsonMetadata::load_or_init(&path)?;
+    assert_eq!(loaded.version(), SemVer::parse("2.10.0")?);
+
+    Ok(())
+}